@@ -1,111 +1,824 @@
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
 use rand::prelude::SliceRandom;
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::JsValue;
 
 use crate::{
-    board::{Board, Player},
+    board::Board,
+    eval,
     moves::Move,
+    pawns::PawnTable,
     piece::Piece,
+    tt::{score_from_tt, score_to_tt, Bound, TranspositionTable},
+    utils::now_ms,
 };
 
-pub const DEPTH: usize = 4;
+/// Deepest iteration when no depth limit is given.
+pub const MAX_DEPTH: usize = 64;
+
+/// How many nodes are searched between two checks of the limits.
+const CHECK_INTERVAL: u64 = 1024;
+
+/// Thinking time in milliseconds of a search from JS without any limit. The
+/// search runs on the main thread there, so it must not run to `MAX_DEPTH`.
+pub const DEFAULT_MOVETIME: u64 = 1000;
+
+/// Ordering scores of the move classes, see `Ai::order_moves`.
+const HASH_MOVE_SCORE: i32 = 3_000_000;
+const CAPTURE_SCORE: i32 = 2_000_000;
+const KILLER_SCORE: i32 = 1_000_000;
+
+/// History scores are halved once one of them reaches this, so quiet moves
+/// never catch up with the killers however long the search runs.
+const MAX_HISTORY: i32 = KILLER_SCORE / 2;
+
+/// Safety margin of delta pruning, in centipawns. A capture is skipped when
+/// even winning the captured piece plus this margin cannot raise alpha.
+const DELTA_MARGIN: i32 = 200;
+
+/// Bigger than any score, used as the initial search window.
+const INFINITY: i32 = 1_000_000;
+
+/// The score of checkmating right now, in centipawns. A mate `n` plies away
+/// is worth `MATE_SCORE - n`, so the search prefers the fastest mate and the
+/// slowest loss.
+pub const MATE_SCORE: i32 = 100_000;
+
+/// Scores at least this far from zero are mates. Leaves room for far more
+/// plies than any search reaches.
+const MATE_BOUND: i32 = MATE_SCORE - 1_000;
+
+/// When to stop searching. All limits are optional, a search without any
+/// limit runs until `MAX_DEPTH` is reached or it is stopped through the flag.
+#[derive(Debug, Clone, Default)]
+pub struct SearchLimits {
+    /// The deepest iteration to search.
+    pub depth: Option<usize>,
+    /// The number of nodes after which the search stops.
+    pub nodes: Option<u64>,
+    /// The time in milliseconds the search may take.
+    pub movetime: Option<u64>,
+    /// The wall clock time in milliseconds since the Unix epoch at which the
+    /// search has to be done.
+    pub deadline: Option<f64>,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: usize) -> SearchLimits {
+        SearchLimits {
+            depth: Some(depth),
+            ..SearchLimits::default()
+        }
+    }
+
+    pub fn movetime(movetime: u64) -> SearchLimits {
+        SearchLimits {
+            movetime: Some(movetime),
+            ..SearchLimits::default()
+        }
+    }
+}
+
+/// The outcome of a completed iteration.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    #[wasm_bindgen(skip)]
+    pub best_move: Move,
+    /// The expected reply to the best move, to ponder on.
+    #[wasm_bindgen(skip)]
+    pub ponder_move: Option<Move>,
+    /// The score in centipawns from the point of view of the side to move.
+    pub score: i32,
+    /// The number of moves until the side to move mates, negative when it
+    /// gets mated, or `None` if no mate was found.
+    pub mate: Option<i32>,
+    pub depth: usize,
+    /// The deepest ply reached, quiescence search included.
+    pub seldepth: usize,
+    pub nodes: u64,
+    /// Nodes per second, 0 while too little time passed to measure it.
+    pub nps: u64,
+    /// Milliseconds since the search started.
+    pub time: u64,
+    /// The principal variation: the best move and the line expected to
+    /// follow it.
+    #[wasm_bindgen(skip)]
+    pub pv: Vec<Move>,
+    /// The best lines ranked by score, as many as `Ai::multi_pv` asks for
+    /// and there are legal moves. The first one is the principal variation.
+    #[wasm_bindgen(skip)]
+    pub lines: Vec<PvLine>,
+}
+
+/// One of the ranked lines of a multi-PV search.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct PvLine {
+    /// The score in centipawns from the point of view of the side to move.
+    pub score: i32,
+    /// The number of moves until mate, see `SearchResult::mate`.
+    pub mate: Option<i32>,
+    /// The candidate move followed by the line expected after it.
+    #[wasm_bindgen(skip)]
+    pub pv: Vec<Move>,
+}
+
+#[wasm_bindgen]
+impl PvLine {
+    #[wasm_bindgen(getter = pv)]
+    pub fn js_pv(&self) -> js_sys::Array {
+        self.pv.iter().cloned().map(JsValue::from).collect()
+    }
+}
 
+#[wasm_bindgen]
+impl SearchResult {
+    #[wasm_bindgen(getter = bestMove)]
+    pub fn js_best_move(&self) -> Move {
+        self.best_move.clone()
+    }
+
+    #[wasm_bindgen(getter = ponderMove)]
+    pub fn js_ponder_move(&self) -> Option<Move> {
+        self.ponder_move.clone()
+    }
+
+    #[wasm_bindgen(getter = pv)]
+    pub fn js_pv(&self) -> js_sys::Array {
+        self.pv.iter().cloned().map(JsValue::from).collect()
+    }
+
+    #[wasm_bindgen(getter = lines)]
+    pub fn js_lines(&self) -> js_sys::Array {
+        self.lines.iter().cloned().map(JsValue::from).collect()
+    }
+}
+
+/// Whether `score` means that one side can force mate.
+pub fn is_mate_score(score: i32) -> bool {
+    score.abs() >= MATE_BOUND
+}
+
+/// Turns a mate score into the number of moves until mate, counted from the
+/// side to move. Positive when the side to move mates, negative when it gets
+/// mated.
+pub fn mate_in(score: i32) -> Option<i32> {
+    if !is_mate_score(score) {
+        return None;
+    }
+
+    let plies = MATE_SCORE - score.abs();
+    Some(if score > 0 {
+        (plies + 1) / 2
+    } else {
+        -(plies / 2)
+    })
+}
+
+#[wasm_bindgen]
 pub struct Ai {
-    player: Player,
-    color: Piece,
+    tt: TranspositionTable,
+    /// How many of the best moves are searched with their own line.
+    multi_pv: usize,
+    stop: Arc<AtomicBool>,
+    /// Nodes visited in the current search.
+    nodes: u64,
+    /// The deepest ply reached in the current iteration.
+    seldepth: usize,
+    /// The limits of the current search, with the time limits turned into
+    /// a single deadline.
+    node_limit: Option<u64>,
+    deadline: Option<f64>,
+    /// Set once a limit was hit, the running iteration is thrown away then.
+    aborted: bool,
+    /// Whether the limits apply to the running iteration. The first iteration
+    /// always completes, so there is a move to play.
+    abortable: bool,
+    /// Two quiet moves per ply which recently caused a beta cutoff, as they
+    /// likely do so in the sibling positions too.
+    killers: [[Option<Move>; 2]; MAX_DEPTH],
+    /// How often quiet moves caused cutoffs, indexed by start and end square
+    /// and weighted by the depth.
+    history: Box<[[i32; 64]; 64]>,
+    pawns: PawnTable,
 }
 
+#[wasm_bindgen]
 impl Ai {
-    pub fn new(player: Player) -> Ai {
-        let color = if let Player::White = player {
-            Piece::WHITE
+    #[wasm_bindgen(constructor)]
+    pub fn js_new() -> Ai {
+        Ai::new()
+    }
+
+    /// Searches `board` at most `depth` plies deep and for at most
+    /// `movetime` milliseconds, or for `DEFAULT_MOVETIME` without limits.
+    #[wasm_bindgen(js_name = search)]
+    pub fn js_search(
+        &mut self,
+        board: &Board,
+        depth: Option<usize>,
+        movetime: Option<u32>,
+    ) -> Option<SearchResult> {
+        let movetime = match (depth, movetime) {
+            (None, None) => Some(DEFAULT_MOVETIME),
+            (_, movetime) => movetime.map(u64::from),
+        };
+        let limits = SearchLimits {
+            depth,
+            movetime,
+            ..SearchLimits::default()
+        };
+        self.search(board, &limits)
+    }
+
+    #[wasm_bindgen(js_name = setMultiPv)]
+    pub fn js_set_multi_pv(&mut self, lines: usize) {
+        self.set_multi_pv(lines);
+    }
+}
+
+impl Default for Ai {
+    fn default() -> Self {
+        Ai::with_table(TranspositionTable::default())
+    }
+}
+
+impl Ai {
+    /// An AI for either side, it always searches for the side to move.
+    pub fn new() -> Ai {
+        Ai::default()
+    }
+
+    pub fn with_table(tt: TranspositionTable) -> Ai {
+        Ai {
+            tt,
+            multi_pv: 1,
+            stop: Arc::new(AtomicBool::new(false)),
+            nodes: 0,
+            seldepth: 0,
+            node_limit: None,
+            deadline: None,
+            aborted: false,
+            abortable: false,
+            killers: std::array::from_fn(|_| [None, None]),
+            history: Box::new([[0; 64]; 64]),
+            pawns: PawnTable::default(),
+        }
+    }
+
+    pub fn multi_pv(&self) -> usize {
+        self.multi_pv
+    }
+
+    /// Makes the search rank the best `lines` moves, each with its own score
+    /// and principal variation, instead of only the best one. Every extra
+    /// line costs about as much as another search of the same depth.
+    pub fn set_multi_pv(&mut self, lines: usize) {
+        self.multi_pv = lines.max(1);
+    }
+
+    pub fn tt(&self) -> &TranspositionTable {
+        &self.tt
+    }
+
+    pub fn tt_mut(&mut self) -> &mut TranspositionTable {
+        &mut self.tt
+    }
+
+    /// Sets the flag another thread can raise to stop the search. The search
+    /// never lowers the flag itself.
+    pub fn set_stop_flag(&mut self, stop: Arc<AtomicBool>) {
+        self.stop = stop;
+    }
+
+    /// The score of a finished game `ply` plies below the root from the point
+    /// of view of the side to move, or `None` while the game goes on.
+    fn game_over_score(&self, board: &Board, ply: usize) -> Option<i32> {
+        let status = board.status();
+        if !status.is_over() {
+            return None;
+        }
+
+        Some(match status.winner() {
+            Some(winner) if winner == board.turn => MATE_SCORE - ply as i32,
+            Some(_) => -(MATE_SCORE - ply as i32),
+            None => 0,
+        })
+    }
+
+    /// Negamax alpha-beta search. Scores are always from the point of view
+    /// of the side to move, so a child's score is negated and its window is
+    /// the negated and swapped window of the parent.
+    ///
+    /// The line of the best move found inside the window is left in `pv`.
+    fn negamax(
+        &mut self,
+        board: &mut Board,
+        depth: usize,
+        ply: usize,
+        mut alpha: i32,
+        mut beta: i32,
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        // Quiescence counts the node and checks for the end of the game itself
+        if depth == 0 {
+            return self.quiescence(board, ply, alpha, beta);
+        }
+
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+        if self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            self.check_limits();
+        }
+        if self.aborted {
+            return 0;
+        }
+
+        if let Some(score) = self.game_over_score(board, ply) {
+            return score;
+        }
+
+        // Mate distance pruning: no line from here can beat mating on the
+        // next move or be worse than getting mated right now
+        alpha = alpha.max(-(MATE_SCORE - ply as i32));
+        beta = beta.min(MATE_SCORE - ply as i32 - 1);
+        if alpha >= beta {
+            return alpha;
+        }
+
+        let alpha_start = alpha;
+        let hash = board.hash();
+
+        let mut hash_move = None;
+        if let Some(entry) = self.tt.probe(hash) {
+            if entry.depth as usize >= depth {
+                let score = score_from_tt(entry.score, ply);
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => {}
+                }
+            }
+            hash_move = entry.best_move.clone();
+        }
+
+        let mut best_move = None;
+        let mut best_score = -INFINITY;
+
+        let mut moves = board.generate_all_moves();
+        self.order_moves(board, &mut moves, hash_move.as_ref(), ply);
+
+        for move_ in moves {
+            let mut line = Vec::new();
+            let undo = board.make_move(&move_);
+            let score = -self.negamax(board, depth - 1, ply + 1, -beta, -alpha, &mut line);
+            board.unmake_move(&move_, undo);
+
+            if score > alpha {
+                pv.clear();
+                pv.push(move_.clone());
+                pv.append(&mut line);
+            }
+            if best_move.is_none() || score > best_score {
+                best_score = score;
+                best_move = Some(move_);
+            }
+            alpha = alpha.max(best_score);
+
+            if alpha >= beta {
+                // Beta cutoff
+                if let Some(move_) = &best_move {
+                    if !is_capture(board, move_) {
+                        self.record_cutoff(move_, depth, ply);
+                    }
+                }
+                break;
+            }
+        }
+
+        if self.aborted {
+            return 0;
+        }
+
+        let bound = if best_score <= alpha_start {
+            Bound::Upper
+        } else if best_score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.tt
+            .store(hash, depth, bound, score_to_tt(best_score, ply), best_move);
+
+        best_score
+    }
+
+    /// Searches captures and promotions until the position is quiet, so the
+    /// evaluation never happens in the middle of an exchange.
+    fn quiescence(&mut self, board: &mut Board, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+        if self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            self.check_limits();
+        }
+        if self.aborted {
+            return 0;
+        }
+
+        // When in check, every evasion has to be searched and standing pat is
+        // not an option, as the check might be mate
+        let in_check = board.in_check();
+        let mut moves = if in_check {
+            board.generate_all_moves()
+        } else {
+            board.generate_captures()
+        };
+
+        // Unlike `Board::status`, this reuses the moves generated anyway. A
+        // stalemate would need all quiet moves too, so it is left to the
+        // main search
+        if in_check && moves.is_empty() {
+            return -(MATE_SCORE - ply as i32);
+        }
+        if board.has_insufficient_material()
+            || board.halfmove_clock() >= 100
+            || board.repetitions() >= 3
+        {
+            return 0;
+        }
+
+        let stand_pat = if in_check {
+            -INFINITY
         } else {
-            Piece::BLACK
+            eval::evaluate_cached(board, &mut self.pawns)
         };
-        Ai { player, color }
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+        moves.sort_by_cached_key(|move_| Reverse(mvv_lva(board, move_)));
+
+        let mut best_score = stand_pat;
+        for move_ in moves {
+            if !in_check {
+                let mut gain = if move_.is_en_passant(board) {
+                    eval::piece_value(Piece::PAWN)
+                } else {
+                    eval::piece_value(board[&move_.end])
+                };
+                if let Some(promotion) = move_.promotion() {
+                    gain += eval::piece_value(promotion) - eval::piece_value(Piece::PAWN);
+                }
+
+                if stand_pat + gain + DELTA_MARGIN < alpha {
+                    continue; // Delta pruning
+                }
+            }
+
+            let undo = board.make_move(&move_);
+            let score = -self.quiescence(board, ply + 1, -beta, -alpha);
+            board.unmake_move(&move_, undo);
+
+            best_score = best_score.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        best_score
     }
 
-    fn eval(&self, board: &Board) -> f32 {
-        let mut score: f32 = 0.0;
-        for pos in board.positions() {
-            let piece = board[pos];
-            if piece.color() == self.color {
-                score += piece.score() as f32;
+    /// Sorts `moves` so the ones most likely to cause a cutoff come first:
+    /// the hash move, then captures by MVV-LVA, then the killer moves of
+    /// this ply and finally the other quiet moves by their history score.
+    fn order_moves(&self, board: &Board, moves: &mut [Move], hash_move: Option<&Move>, ply: usize) {
+        let killers = self.killers.get(ply);
+
+        moves.sort_by_cached_key(|move_| {
+            let score = if Some(move_) == hash_move {
+                HASH_MOVE_SCORE
+            } else if is_capture(board, move_) {
+                CAPTURE_SCORE + mvv_lva(board, move_)
+            } else if let Some(slot) =
+                killers.and_then(|killers| killers.iter().position(|k| k.as_ref() == Some(move_)))
+            {
+                KILLER_SCORE - slot as i32
             } else {
-                score -= piece.score() as f32;
+                self.history[move_.start.index()][move_.end.index()]
+            };
+            Reverse(score)
+        });
+    }
+
+    /// Remembers a quiet move which caused a beta cutoff as killer of its ply
+    /// and raises its history score.
+    fn record_cutoff(&mut self, move_: &Move, depth: usize, ply: usize) {
+        if let Some(killers) = self.killers.get_mut(ply) {
+            if killers[0].as_ref() != Some(move_) {
+                killers[1] = killers[0].take();
+                killers[0] = Some(move_.clone());
             }
         }
 
-        let move_count = board.generate_all_moves().len();
+        let history = &mut self.history[move_.start.index()][move_.end.index()];
+        *history += (depth * depth) as i32;
+        if *history >= MAX_HISTORY {
+            self.age_history();
+        }
+    }
+
+    fn age_history(&mut self) {
+        for score in self.history.iter_mut().flatten() {
+            *score /= 2;
+        }
+    }
+
+    /// Searches `depth` plies deep and returns the score from the point of
+    /// view of the side to move.
+    pub fn eval_board(&mut self, board: &Board, depth: usize) -> i32 {
+        let mut board = board.clone();
+        self.start_search(&SearchLimits::default());
+        self.negamax(&mut board, depth, 0, -INFINITY, INFINITY, &mut Vec::new())
+    }
 
-        score += 0.1 * move_count as f32;
+    pub fn best_move(&mut self, board: &Board, limits: &SearchLimits) -> Option<Move> {
+        self.search(board, limits).map(|result| result.best_move)
+    }
 
-        score
+    /// Searches with iterative deepening until one of the limits is hit, and
+    /// returns the result of the last completed iteration.
+    pub fn search(&mut self, board: &Board, limits: &SearchLimits) -> Option<SearchResult> {
+        self.search_with(board, limits, |_| {})
     }
 
-    fn alpha_beta_negamax(
-        &self,
+    /// Like `search`, but calls `on_iteration` after every completed
+    /// iteration, e.g. for reporting the progress.
+    pub fn search_with(
+        &mut self,
         board: &Board,
-        depth: usize,
-        mut alpha: f32,
-        mut beta: f32,
-        max_player: bool,
-    ) -> f32 {
-        if depth == 0 || board.is_game_over() {
-            return self.eval(board);
-        }
-
-        if max_player {
-            let mut val = -f32::INFINITY;
-            for move_ in board.generate_all_moves() {
-                let mut board_ = board.clone();
-                move_.execute(&mut board_);
-                val = val.max(self.alpha_beta_negamax(&board_, depth - 1, -beta, -alpha, false));
-                if val >= beta {
-                    break; // Beta Cutoff
-                }
-                alpha = alpha.max(val);
+        limits: &SearchLimits,
+        mut on_iteration: impl FnMut(&SearchResult),
+    ) -> Option<SearchResult> {
+        let start = now_ms();
+        self.start_search(limits);
+
+        let mut result = None;
+        for depth in 1..=limits.depth.unwrap_or(MAX_DEPTH) {
+            self.abortable = depth > 1;
+            self.seldepth = 0;
+
+            // Every further line is the best one among the moves not ranked yet
+            let mut lines: Vec<PvLine> = Vec::new();
+            while lines.len() < self.multi_pv {
+                let ranked: Vec<Move> = lines.iter().map(|line| line.pv[0].clone()).collect();
+                let Some((mut pv, score)) = self.search_root(board, depth, &ranked) else {
+                    break;
+                };
+                self.extend_pv(board, &mut pv, depth);
+                lines.push(PvLine {
+                    score,
+                    mate: mate_in(score),
+                    pv,
+                });
             }
-            val
-        } else {
-            let mut val = f32::INFINITY;
-            for move_ in board.generate_all_moves() {
-                let mut board_ = board.clone();
-                move_.execute(&mut board_);
-                val = val.min(self.alpha_beta_negamax(&board_, depth - 1, -beta, -alpha, true));
-                if val <= alpha {
-                    break; // Alpha Cutoff
+            if self.aborted || lines.is_empty() {
+                break;
+            }
+
+            let best = &lines[0];
+            let time = (now_ms() - start).max(0.0) as u64;
+            let iteration = SearchResult {
+                best_move: best.pv[0].clone(),
+                ponder_move: best.pv.get(1).cloned(),
+                score: best.score,
+                mate: best.mate,
+                depth,
+                seldepth: self.seldepth.max(depth),
+                nodes: self.nodes,
+                nps: (self.nodes * 1000).checked_div(time).unwrap_or(0),
+                time,
+                pv: best.pv.clone(),
+                lines,
+            };
+            on_iteration(&iteration);
+            let mate_plies = MATE_SCORE - iteration.score.abs();
+            let mate_proven = is_mate_score(iteration.score) && mate_plies as usize <= depth;
+            result = Some(iteration);
+
+            // Every line was searched to its full depth, so deeper iterations
+            // cannot find a faster mate or a way out
+            if mate_proven {
+                break;
+            }
+
+            // The next iteration takes several times as long as this one, so
+            // there is no point in starting it when half the time is used up
+            if let Some(deadline) = self.deadline {
+                if now_ms() - start >= (deadline - start) / 2.0 {
+                    break;
                 }
-                beta = beta.min(val);
             }
-            val
+            if self.stop.load(Ordering::Relaxed) {
+                break;
+            }
         }
+
+        result
     }
 
-    pub fn eval_board(&self, board: &Board) -> f32 {
-        self.alpha_beta_negamax(board, DEPTH, -f32::INFINITY, f32::INFINITY, true)
+    fn start_search(&mut self, limits: &SearchLimits) {
+        let movetime = limits.movetime.map(|movetime| now_ms() + movetime as f64);
+        self.deadline = match (movetime, limits.deadline) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.node_limit = limits.nodes;
+        self.nodes = 0;
+
+        // Killers are specific to the position, old history still helps a bit
+        self.killers = std::array::from_fn(|_| [None, None]);
+        self.age_history();
+        self.aborted = false;
+        self.abortable = false;
     }
 
-    pub fn best_move(&self, board: &Board) -> Option<Move> {
-        let mut best_move = None;
-        let mut best_score = -f32::INFINITY;
-        for move_ in board.generate_all_moves() {
-            let mut board_ = board.clone();
-            move_.execute(&mut board_);
-            let score = self.eval_board(&board_);
-            if score > best_score {
-                best_move = Some(move_);
+    fn check_limits(&mut self) {
+        self.aborted = self.abortable
+            && (self.stop.load(Ordering::Relaxed)
+                || self.node_limit.is_some_and(|limit| self.nodes >= limit)
+                || self.deadline.is_some_and(|deadline| now_ms() >= deadline));
+    }
+
+    /// Searches all moves except the `excluded` ones `depth` plies deep and
+    /// returns the principal variation, starting with the best move, together
+    /// with its score from the point of view of the side to move.
+    fn search_root(
+        &mut self,
+        board: &Board,
+        depth: usize,
+        excluded: &[Move],
+    ) -> Option<(Vec<Move>, i32)> {
+        // A single copy is searched, every move is taken back after its subtree
+        let mut board = board.clone();
+        let hash = board.hash();
+        let hash_move = self
+            .tt
+            .probe(hash)
+            .and_then(|entry| entry.best_move.clone());
+
+        let mut pv = Vec::new();
+        let mut best_score = -INFINITY;
+        let mut moves = board.generate_all_moves();
+        moves.retain(|move_| !excluded.contains(move_));
+        self.order_moves(&board, &mut moves, hash_move.as_ref(), 0);
+
+        for move_ in moves {
+            let mut line = Vec::new();
+            let undo = board.make_move(&move_);
+            let score = -self.negamax(&mut board, depth - 1, 1, -INFINITY, -best_score, &mut line);
+            board.unmake_move(&move_, undo);
+
+            if self.aborted {
+                return None;
+            }
+
+            if pv.is_empty() || score > best_score {
+                pv = vec![move_];
+                pv.append(&mut line);
                 best_score = score;
             }
         }
-        best_move
+
+        // The score of a search with excluded moves is not the position's
+        let best_move = pv.first()?.clone();
+        if excluded.is_empty() {
+            self.tt
+                .store(hash, depth, Bound::Exact, best_score, Some(best_move));
+        }
+        Some((pv, best_score))
+    }
+
+    /// Lines cut short by a transposition table hit are continued with the
+    /// hash moves, up to `depth` moves in total.
+    fn extend_pv(&mut self, board: &Board, pv: &mut Vec<Move>, depth: usize) {
+        let mut board = board.clone();
+        for move_ in pv.iter() {
+            board.make_move(move_);
+        }
+
+        while pv.len() < depth {
+            let Some(move_) = self
+                .tt
+                .probe(board.hash())
+                .and_then(|entry| entry.best_move.clone())
+            else {
+                break;
+            };
+            // A different position with the same index might have stored it
+            if !board.generate_all_moves().contains(&move_) {
+                break;
+            }
+
+            board.make_move(&move_);
+            pv.push(move_);
+        }
     }
 
-    pub fn best_move_or_random(&self, board: &Board) -> Move {
-        let best_move = self.best_move(board);
-        if best_move.is_some() {
-            best_move.unwrap()
+    pub fn best_move_or_random(&mut self, board: &Board, limits: &SearchLimits) -> Move {
+        if let Some(best_move) = self.best_move(board, limits) {
+            best_move
         } else {
             let moves = board.generate_all_moves();
             moves.choose(&mut rand::thread_rng()).unwrap().clone()
         }
     }
 }
+
+/// Whether `move_` takes a piece, en passant included, or promotes.
+fn is_capture(board: &Board, move_: &Move) -> bool {
+    board[&move_.end] != Piece::NONE || move_.is_en_passant(board) || move_.promotion.is_some()
+}
+
+/// Most valuable victim, least valuable attacker: captures of big pieces come
+/// first, and among those the ones made with the smallest piece.
+fn mvv_lva(board: &Board, move_: &Move) -> i32 {
+    let victim = if move_.is_en_passant(board) {
+        Piece::PAWN
+    } else {
+        board[&move_.end]
+    };
+    let promotion = move_.promotion.map_or(0, |piece| piece.score());
+
+    10 * (victim.score() + promotion) - board[&move_.start].score()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ordered(ai: &Ai, board: &Board, hash_move: Option<&Move>, ply: usize) -> Vec<String> {
+        let mut moves = board.generate_all_moves();
+        ai.order_moves(board, &mut moves, hash_move, ply);
+        moves.iter().map(|move_| move_.to_uci()).collect()
+    }
+
+    #[test]
+    fn captures_by_most_valuable_victim_then_least_valuable_attacker() {
+        let board = Board::from_fen("4k3/8/8/2q3r1/1P1P1P2/8/8/2R1K3 w - - 0 1").unwrap();
+        let ai = Ai::new();
+        let moves = ordered(&ai, &board, None, 0);
+
+        let mut pawn_takes_queen = moves[..2].to_vec();
+        pawn_takes_queen.sort();
+        assert_eq!(pawn_takes_queen, ["b4c5", "d4c5"]);
+        assert_eq!(moves[2..4], ["c1c5", "f4g5"]);
+
+        // The hash move goes first, whatever it is
+        let hash_move = Move::from_uci("e1f2").unwrap();
+        let moves = ordered(&ai, &board, Some(&hash_move), 0);
+        assert_eq!(moves[0], "e1f2");
+        assert_eq!(moves[3..5], ["c1c5", "f4g5"]);
+    }
+
+    #[test]
+    fn cutoff_moves_go_before_other_quiet_moves() {
+        let board = Board::from_fen("4k3/8/8/2q3r1/1P1P1P2/8/8/2R1K3 w - - 0 1").unwrap();
+        let mut ai = Ai::new();
+        let killer = Move::from_uci("c1c4").unwrap();
+        ai.record_cutoff(&killer, 3, 2);
+
+        // As a killer right behind the captures on its own ply
+        let moves = ordered(&ai, &board, None, 2);
+        assert_eq!(moves[4], "c1c4");
+
+        // And by its history score still ahead of the other quiet moves on
+        // any other ply
+        let moves = ordered(&ai, &board, None, 5);
+        assert_eq!(moves[4], "c1c4");
+
+        // A second killer ranks behind the newer one
+        ai.record_cutoff(&Move::from_uci("e1d2").unwrap(), 1, 2);
+        let moves = ordered(&ai, &board, None, 2);
+        assert_eq!(moves[4..6], ["e1d2", "c1c4"]);
+
+        // However often a quiet move causes cutoffs elsewhere, it stays
+        // behind the killers
+        let quiet = Move::from_uci("e1f1").unwrap();
+        for _ in 0..10_000 {
+            ai.record_cutoff(&quiet, MAX_DEPTH - 1, 10);
+        }
+        assert!(ai
+            .history
+            .iter()
+            .flatten()
+            .all(|&score| score < MAX_HISTORY));
+        let moves = ordered(&ai, &board, None, 2);
+        assert_eq!(moves[4..7], ["e1d2", "c1c4", "e1f1"]);
+    }
+}
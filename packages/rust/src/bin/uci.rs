@@ -0,0 +1,433 @@
+//! A UCI engine on top of the `Board` and `Ai` of this crate, so the AI can be
+//! used from chess GUIs and tested against other engines.
+//!
+//! Only the commands needed to play games are supported: `uci`, `isready`,
+//! `setoption`, `ucinewgame`, `position`, `go`, `stop` and `quit`. The
+//! options are `Hash`, the size of the transposition table in megabytes, and
+//! `MultiPV`, the number of best lines to report.
+
+use std::io::{self, BufRead};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use vite_wasm_functions::ai::{Ai, SearchLimits};
+use vite_wasm_functions::board::{Board, Player};
+use vite_wasm_functions::moves::Move;
+use vite_wasm_functions::tt::{self, TranspositionTable};
+
+const NAME: &str = "wasm-chess";
+const AUTHOR: &str = "Michael Finger";
+
+/// Bounds of the `Hash` option, in megabytes.
+const MAX_HASH_MB: usize = 1024;
+
+/// Upper bound of the `MultiPV` option.
+const MAX_MULTI_PV: usize = 64;
+
+/// Expected number of moves left in the game when the GUI does not tell us.
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+
+#[derive(Debug, Default)]
+struct GoOptions {
+    depth: Option<usize>,
+    nodes: Option<u64>,
+    movetime: Option<u64>,
+    wtime: Option<u64>,
+    btime: Option<u64>,
+    winc: Option<u64>,
+    binc: Option<u64>,
+    movestogo: Option<u64>,
+    infinite: bool,
+}
+
+impl GoOptions {
+    fn parse<'a>(mut args: impl Iterator<Item = &'a str>) -> GoOptions {
+        let mut options = GoOptions::default();
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().and_then(|v| v.parse::<u64>().ok());
+            match arg {
+                "depth" => options.depth = value().map(|d| d as usize),
+                "nodes" => options.nodes = value(),
+                "movetime" => options.movetime = value(),
+                "wtime" => options.wtime = value(),
+                "btime" => options.btime = value(),
+                "winc" => options.winc = value(),
+                "binc" => options.binc = value(),
+                "movestogo" => options.movestogo = value(),
+                "infinite" => options.infinite = true,
+                _ => {}
+            }
+        }
+
+        options
+    }
+
+    /// How many milliseconds we may think about the current move, if at all
+    /// limited.
+    fn time_budget(&self, turn: Player) -> Option<u64> {
+        if self.infinite {
+            return None;
+        }
+        if let Some(movetime) = self.movetime {
+            return Some(movetime);
+        }
+
+        let (time, inc) = match turn {
+            Player::White => (self.wtime?, self.winc.unwrap_or(0)),
+            Player::Black => (self.btime?, self.binc.unwrap_or(0)),
+        };
+        let moves_to_go = self.movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+
+        // Keep a little reserve so we never lose on time because of overhead
+        let budget = time / moves_to_go + inc / 2;
+        Some(budget.min(time.saturating_sub(50)))
+    }
+}
+
+struct Engine {
+    board: Board,
+    /// Kept between searches so the transposition table stays filled. It is
+    /// lent to the search thread while a search is running.
+    ai: Option<Ai>,
+    search: Option<(Arc<AtomicBool>, JoinHandle<Ai>)>,
+}
+
+impl Engine {
+    fn new() -> Engine {
+        Engine {
+            board: Board::default(),
+            ai: Some(Ai::new()),
+            search: None,
+        }
+    }
+
+    fn ai(&mut self) -> &mut Ai {
+        self.ai.get_or_insert_with(Ai::new)
+    }
+
+    /// Handles a single command, returning `false` once the engine should quit.
+    fn handle(&mut self, line: &str) -> bool {
+        let mut args = line.split_whitespace();
+
+        match args.next() {
+            Some("uci") => {
+                println!("id name {}", NAME);
+                println!("id author {}", AUTHOR);
+                println!(
+                    "option name Hash type spin default {} min 1 max {}",
+                    tt::DEFAULT_SIZE_MB,
+                    MAX_HASH_MB
+                );
+                println!(
+                    "option name MultiPV type spin default 1 min 1 max {}",
+                    MAX_MULTI_PV
+                );
+                println!("uciok");
+            }
+            Some("isready") => println!("readyok"),
+            Some("setoption") => {
+                self.stop();
+                self.set_option(args);
+            }
+            Some("ucinewgame") => {
+                self.stop();
+                self.board = Board::default();
+                self.ai().tt_mut().clear();
+            }
+            Some("position") => {
+                self.stop();
+                self.set_position(args);
+            }
+            Some("go") => {
+                self.stop();
+                self.go(GoOptions::parse(args));
+            }
+            Some("stop") => self.stop(),
+            Some("quit") => {
+                self.stop();
+                return false;
+            }
+            _ => {}
+        }
+
+        true
+    }
+
+    /// Handles `setoption name <name> value <value>`.
+    fn set_option<'a>(&mut self, args: impl Iterator<Item = &'a str>) {
+        let args: Vec<&str> = args.collect();
+        match args.as_slice() {
+            ["name", name, "value", value] if name.eq_ignore_ascii_case("hash") => {
+                match value.parse::<usize>() {
+                    Ok(megabytes) => {
+                        let megabytes = megabytes.clamp(1, MAX_HASH_MB);
+                        let multi_pv = self.ai().multi_pv();
+                        let mut ai = Ai::with_table(TranspositionTable::new(megabytes));
+                        ai.set_multi_pv(multi_pv);
+                        self.ai = Some(ai);
+                    }
+                    Err(_) => println!("info string invalid hash size {}", value),
+                }
+            }
+            ["name", name, "value", value] if name.eq_ignore_ascii_case("multipv") => {
+                match value.parse::<usize>() {
+                    Ok(lines) => self.ai().set_multi_pv(lines.clamp(1, MAX_MULTI_PV)),
+                    Err(_) => println!("info string invalid number of lines {}", value),
+                }
+            }
+            _ => println!("info string unknown option"),
+        }
+    }
+
+    /// Handles `position [startpos | fen <fen>] [moves <move>...]`.
+    fn set_position<'a>(&mut self, args: impl Iterator<Item = &'a str>) {
+        let args: Vec<&str> = args.collect();
+        let moves_at = args.iter().position(|&arg| arg == "moves");
+        let (setup, moves) = args.split_at(moves_at.unwrap_or(args.len()));
+
+        let board = match setup {
+            ["startpos"] => Board::default(),
+            ["fen", fen @ ..] => match Board::from_fen(&fen.join(" ")) {
+                Ok(board) => board,
+                Err(err) => {
+                    println!("info string {}", err);
+                    return;
+                }
+            },
+            _ => {
+                println!("info string invalid position command");
+                return;
+            }
+        };
+        self.board = board;
+
+        for uci in moves.iter().skip(1) {
+            let legal = Move::from_uci(uci)
+                .ok()
+                .filter(|move_| self.board.generate_all_moves().contains(move_));
+
+            match legal {
+                Some(move_) => move_.execute(&mut self.board),
+                None => {
+                    println!("info string illegal move {}", uci);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn go(&mut self, options: GoOptions) {
+        let board = self.board.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        let limits = SearchLimits {
+            depth: options.depth,
+            nodes: options.nodes,
+            movetime: options.time_budget(board.turn),
+            deadline: None,
+        };
+
+        let infinite = options.infinite;
+
+        let mut ai = self.ai.take().unwrap_or_default();
+        ai.set_stop_flag(Arc::clone(&stop));
+        ai.tt_mut().reset_stats();
+
+        let stopped = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            let result = ai.search_with(&board, &limits, |iteration| {
+                for (rank, line) in iteration.lines.iter().enumerate() {
+                    let score = match line.mate {
+                        Some(moves) => format!("mate {}", moves),
+                        None => format!("cp {}", line.score),
+                    };
+                    let pv: Vec<String> = line.pv.iter().map(Move::to_uci).collect();
+                    println!(
+                        "info depth {} seldepth {} multipv {} score {} nodes {} nps {} time {} pv {}",
+                        iteration.depth,
+                        iteration.seldepth,
+                        rank + 1,
+                        score,
+                        iteration.nodes,
+                        iteration.nps,
+                        iteration.time,
+                        pv.join(" ")
+                    );
+                }
+            });
+
+            let stats = ai.tt().stats();
+            println!("info hashfull {}", ai.tt().hashfull());
+            println!(
+                "info string hash probes {} hits {} ({:.1}%)",
+                stats.probes,
+                stats.hits,
+                stats.hit_rate() * 100.0
+            );
+
+            // An infinite search may end by itself, e.g. on a proven mate,
+            // but the GUI expects the best move only after `stop`
+            while infinite && !stopped.load(Ordering::Relaxed) {
+                thread::park();
+            }
+
+            match result {
+                Some(result) => match result.ponder_move {
+                    Some(ponder) => println!(
+                        "bestmove {} ponder {}",
+                        result.best_move.to_uci(),
+                        ponder.to_uci()
+                    ),
+                    None => println!("bestmove {}", result.best_move.to_uci()),
+                },
+                None => println!("bestmove 0000"),
+            }
+
+            ai
+        });
+
+        self.search = Some((stop, handle));
+    }
+
+    /// Stops a running search and waits for it to report its best move.
+    fn stop(&mut self) {
+        if let Some((stop, handle)) = self.search.take() {
+            stop.store(true, Ordering::Relaxed);
+            handle.thread().unpark();
+            self.ai = Some(handle.join().expect("search thread panicked"));
+        }
+    }
+}
+
+fn main() {
+    let mut engine = Engine::new();
+
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+
+        if !engine.handle(line.trim()) {
+            return;
+        }
+    }
+
+    engine.stop();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn go(command: &str) -> GoOptions {
+        GoOptions::parse(command.split_whitespace())
+    }
+
+    fn position(engine: &mut Engine, command: &str) -> String {
+        engine.handle(command);
+        engine.board.to_fen()
+    }
+
+    #[test]
+    fn go_options_are_parsed() {
+        let options = go("wtime 60000 btime 50000 winc 1000 binc 500 movestogo 20");
+        assert_eq!(options.wtime, Some(60000));
+        assert_eq!(options.btime, Some(50000));
+        assert_eq!(options.winc, Some(1000));
+        assert_eq!(options.binc, Some(500));
+        assert_eq!(options.movestogo, Some(20));
+        assert_eq!(options.movetime, None);
+        assert!(!options.infinite);
+
+        let options = go("depth 6 nodes 10000 movetime 250");
+        assert_eq!(options.depth, Some(6));
+        assert_eq!(options.nodes, Some(10000));
+        assert_eq!(options.movetime, Some(250));
+
+        assert!(go("infinite").infinite);
+
+        // Unknown words and values which are no numbers are skipped
+        let options = go("ponder wtime soon btime 1000");
+        assert_eq!(options.wtime, None);
+        assert_eq!(options.btime, Some(1000));
+    }
+
+    #[test]
+    fn time_budget() {
+        let options = go("wtime 60000 btime 50000 winc 1000 binc 500 movestogo 20");
+        assert_eq!(options.time_budget(Player::White), Some(3500));
+        assert_eq!(options.time_budget(Player::Black), Some(2750));
+
+        // Without movestogo the rest of the game is guessed
+        let options = go("wtime 30000");
+        assert_eq!(options.time_budget(Player::White), Some(1000));
+        assert_eq!(options.time_budget(Player::Black), None);
+
+        assert_eq!(
+            go("movetime 250 wtime 30000").time_budget(Player::White),
+            Some(250)
+        );
+        assert_eq!(go("infinite movetime 250").time_budget(Player::White), None);
+        assert_eq!(go("depth 6").time_budget(Player::White), None);
+    }
+
+    #[test]
+    fn time_budget_keeps_a_reserve() {
+        // The increment alone would be more than what is left on the clock
+        let options = go("wtime 100 winc 1000");
+        assert_eq!(options.time_budget(Player::White), Some(50));
+
+        let options = go("wtime 30 winc 1000 movestogo 0");
+        assert_eq!(options.time_budget(Player::White), Some(0));
+    }
+
+    #[test]
+    fn position_with_moves() {
+        let mut engine = Engine::new();
+        assert_eq!(
+            position(&mut engine, "position startpos moves e2e4 e7e5 g1f3"),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+        assert_eq!(
+            position(&mut engine, "position startpos"),
+            Board::default().to_fen()
+        );
+
+        assert_eq!(
+            position(
+                &mut engine,
+                "position fen 4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 moves e2e4 e8d7"
+            ),
+            "8/3k4/8/8/4P3/8/8/4K3 w - - 1 2"
+        );
+        assert_eq!(
+            position(&mut engine, "position fen 4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"),
+            "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"
+        );
+    }
+
+    #[test]
+    fn invalid_positions() {
+        let mut engine = Engine::new();
+
+        // Moves are played up to the first illegal one
+        assert_eq!(
+            position(&mut engine, "position startpos moves e2e4 e2e4 e7e5"),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+        );
+        assert_eq!(
+            position(&mut engine, "position startpos moves e2e4 e7e5 x"),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2"
+        );
+
+        // An invalid setup keeps the previous position
+        let before = engine.board.to_fen();
+        assert_eq!(
+            position(&mut engine, "position fen 8/8/8 w - - 0 1"),
+            before
+        );
+        assert_eq!(position(&mut engine, "position"), before);
+    }
+}
@@ -0,0 +1,290 @@
+//! Bitboard helpers and precomputed attack tables.
+//!
+//! A bitboard is a `u64` with one bit per square, bit `y * 8 + x` standing for
+//! the square `Position { x, y }`, so `a1` is bit 0 and `h8` is bit 63.
+//!
+//! Sliding attacks are looked up with magic bitboards: the blockers on the
+//! relevant rays are multiplied with a magic number, which maps every possible
+//! blocker configuration onto its own slot of a precomputed table. The magics
+//! are searched for once at first use instead of being hard-coded.
+
+use std::sync::OnceLock;
+
+pub type Bitboard = u64;
+
+pub const FILE_A: Bitboard = 0x0101_0101_0101_0101;
+pub const FILE_H: Bitboard = FILE_A << 7;
+pub const RANK_1: Bitboard = 0xff;
+pub const RANK_8: Bitboard = RANK_1 << 56;
+
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (-1, 2),
+    (-2, 1),
+    (1, -2),
+    (2, -1),
+    (-1, -2),
+    (-2, -1),
+];
+const KING_OFFSETS: [(i8, i8); 8] = [
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+    (1, 0),
+    (0, 1),
+    (-1, 0),
+    (0, -1),
+];
+/// Seeds for the magic search of the squares on each rank, picked because
+/// they find all magics quickly. An arbitrary seed makes startup much slower.
+const MAGIC_SEEDS: [u64; 8] = [728, 10316, 55013, 32803, 12281, 15100, 16645, 255];
+
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+pub fn bit(square: usize) -> Bitboard {
+    1 << square
+}
+
+/// Iterates over the squares of a bitboard, lowest square first.
+pub struct Squares(Bitboard);
+
+impl Iterator for Squares {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None;
+        }
+
+        let square = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(square)
+    }
+}
+
+pub fn squares(bitboard: Bitboard) -> Squares {
+    Squares(bitboard)
+}
+
+pub fn knight_attacks(square: usize) -> Bitboard {
+    tables().knight[square]
+}
+
+pub fn king_attacks(square: usize) -> Bitboard {
+    tables().king[square]
+}
+
+/// The squares a pawn of the given color index (0 for white, 1 for black)
+/// attacks from `square`.
+pub fn pawn_attacks(color: usize, square: usize) -> Bitboard {
+    tables().pawn[color][square]
+}
+
+pub fn bishop_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    let tables = tables();
+    tables.attacks[tables.bishop[square].index(occupied)]
+}
+
+pub fn rook_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    let tables = tables();
+    tables.attacks[tables.rook[square].index(occupied)]
+}
+
+pub fn queen_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    bishop_attacks(square, occupied) | rook_attacks(square, occupied)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Magic {
+    /// The squares whose occupancy changes the attacks, edges excluded.
+    mask: Bitboard,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    fn index(&self, occupied: Bitboard) -> usize {
+        let blockers = occupied & self.mask;
+        self.offset + (blockers.wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+struct Tables {
+    knight: [Bitboard; 64],
+    king: [Bitboard; 64],
+    pawn: [[Bitboard; 64]; 2],
+    bishop: [Magic; 64],
+    rook: [Magic; 64],
+    /// The sliding attacks of all squares, each square using its own slice.
+    attacks: Vec<Bitboard>,
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(Tables::new)
+}
+
+impl Tables {
+    fn new() -> Tables {
+        let mut tables = Tables {
+            knight: [0; 64],
+            king: [0; 64],
+            pawn: [[0; 64]; 2],
+            bishop: [Magic::default(); 64],
+            rook: [Magic::default(); 64],
+            attacks: Vec::new(),
+        };
+
+        for square in 0..64 {
+            tables.knight[square] = step_attacks(square, &KNIGHT_OFFSETS);
+            tables.king[square] = step_attacks(square, &KING_OFFSETS);
+            tables.pawn[0][square] = step_attacks(square, &[(1, 1), (-1, 1)]);
+            tables.pawn[1][square] = step_attacks(square, &[(1, -1), (-1, -1)]);
+        }
+
+        for square in 0..64 {
+            let seed = MAGIC_SEEDS[square / 8];
+            tables.bishop[square] = find_magic(
+                square,
+                &BISHOP_DIRECTIONS,
+                &mut XorShift(seed),
+                &mut tables.attacks,
+            );
+            tables.rook[square] = find_magic(
+                square,
+                &ROOK_DIRECTIONS,
+                &mut XorShift(seed),
+                &mut tables.attacks,
+            );
+        }
+
+        tables
+    }
+}
+
+fn step_attacks(square: usize, offsets: &[(i8, i8)]) -> Bitboard {
+    let (x, y) = ((square % 8) as i8, (square / 8) as i8);
+
+    offsets
+        .iter()
+        .map(|&(dx, dy)| (x + dx, y + dy))
+        .filter(|&(x, y)| (0..8).contains(&x) && (0..8).contains(&y))
+        .fold(0, |attacks, (x, y)| attacks | bit((y * 8 + x) as usize))
+}
+
+/// Walks the rays from `square` until they hit a blocker, which is included.
+fn ray_attacks(square: usize, directions: &[(i8, i8)], occupied: Bitboard) -> Bitboard {
+    let mut attacks = 0;
+
+    for &(dx, dy) in directions {
+        let (mut x, mut y) = ((square % 8) as i8 + dx, (square / 8) as i8 + dy);
+        while (0..8).contains(&x) && (0..8).contains(&y) {
+            let target = bit((y * 8 + x) as usize);
+            attacks |= target;
+            if occupied & target != 0 {
+                break;
+            }
+            x += dx;
+            y += dy;
+        }
+    }
+
+    attacks
+}
+
+/// The blocker mask of a slider: its empty board attacks without the last
+/// square of every ray, as a piece there never blocks anything.
+fn relevant_mask(square: usize, directions: &[(i8, i8)]) -> Bitboard {
+    let on_board = |x: i8, y: i8| (0..8).contains(&x) && (0..8).contains(&y);
+    let mut mask = 0;
+
+    for &(dx, dy) in directions {
+        let (mut x, mut y) = ((square % 8) as i8 + dx, (square / 8) as i8 + dy);
+        while on_board(x + dx, y + dy) {
+            mask |= bit((y * 8 + x) as usize);
+            x += dx;
+            y += dy;
+        }
+    }
+
+    mask
+}
+
+/// Searches a magic for `square` and appends its attack table to `attacks`.
+fn find_magic(
+    square: usize,
+    directions: &[(i8, i8)],
+    rng: &mut XorShift,
+    attacks: &mut Vec<Bitboard>,
+) -> Magic {
+    let mask = relevant_mask(square, directions);
+    let bits = mask.count_ones();
+
+    // Enumerate all blocker subsets of the mask with the carry-rippler trick
+    let mut occupancies = Vec::with_capacity(1 << bits);
+    let mut subset: Bitboard = 0;
+    loop {
+        occupancies.push((subset, ray_attacks(square, directions, subset)));
+        subset = subset.wrapping_sub(mask) & mask;
+        if subset == 0 {
+            break;
+        }
+    }
+
+    let mut table = vec![0; 1 << bits];
+    let mut used = vec![0u32; 1 << bits];
+    let mut attempt = 0;
+
+    loop {
+        // Sparse numbers make good magics much more likely
+        let magic = rng.next() & rng.next() & rng.next();
+        if (mask.wrapping_mul(magic) >> 56).count_ones() < 6 {
+            continue;
+        }
+
+        attempt += 1;
+        let candidate = Magic {
+            mask,
+            magic,
+            shift: 64 - bits,
+            offset: 0,
+        };
+
+        let fits = occupancies.iter().all(|&(occupied, attack)| {
+            let index = candidate.index(occupied);
+            if used[index] != attempt {
+                used[index] = attempt;
+                table[index] = attack;
+                true
+            } else {
+                table[index] == attack
+            }
+        });
+
+        if fits {
+            let offset = attacks.len();
+            attacks.extend_from_slice(&table);
+            return Magic {
+                offset,
+                ..candidate
+            };
+        }
+    }
+}
+
+/// A small deterministic pseudo random number generator, so the magics are
+/// the same on every run.
+pub(crate) struct XorShift(pub(crate) u64);
+
+impl XorShift {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
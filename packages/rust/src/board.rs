@@ -1,14 +1,21 @@
+use bitflags::bitflags;
 use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen::JsValue;
 
+use crate::bitboard::{
+    bishop_attacks, bit, king_attacks, knight_attacks, pawn_attacks, queen_attacks, rook_attacks,
+    squares, Bitboard, RANK_1, RANK_8,
+};
 use crate::moves::Move;
 use crate::piece::Piece;
-use crate::pos::{self, Position};
+use crate::pos::Position;
+use crate::status::GameStatus;
+use crate::zobrist;
 
 use std::fmt;
 
-#[wasm_bindgen(inspectable)]
-#[derive(Debug, Clone, Copy)]
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Player {
     White,
     Black,
@@ -21,49 +28,105 @@ impl Player {
             Player::Black => Player::White,
         }
     }
+
+    pub fn color(&self) -> Piece {
+        match self {
+            Player::White => Piece::WHITE,
+            Player::Black => Piece::BLACK,
+        }
+    }
+}
+
+bitflags! {
+    pub struct CastlingRights: u8 {
+        const WHITE_KINGSIDE = 0b0001;
+        const WHITE_QUEENSIDE = 0b0010;
+        const BLACK_KINGSIDE = 0b0100;
+        const BLACK_QUEENSIDE = 0b1000;
+
+        const WHITE = 0b0011;
+        const BLACK = 0b1100;
+    }
+}
+
+impl CastlingRights {
+    /// The rights that are lost once the piece on `pos` moves or is captured.
+    pub fn lost_by(pos: &Position) -> CastlingRights {
+        match (pos.x, pos.y) {
+            (4, 0) => CastlingRights::WHITE,
+            (0, 0) => CastlingRights::WHITE_QUEENSIDE,
+            (7, 0) => CastlingRights::WHITE_KINGSIDE,
+            (4, 7) => CastlingRights::BLACK,
+            (0, 7) => CastlingRights::BLACK_QUEENSIDE,
+            (7, 7) => CastlingRights::BLACK_KINGSIDE,
+            _ => CastlingRights::empty(),
+        }
+    }
 }
 
 #[wasm_bindgen]
 #[derive(Debug, Clone)]
 pub struct Board {
-    pieces: [Piece; 64],
+    /// The piece on every square. This mirrors the bitboards below and backs
+    /// the `Index<Position>` access.
+    pub(crate) pieces: [Piece; 64],
+    /// One bitboard per piece kind, indexed by `Piece::kind_index`.
+    by_kind: [Bitboard; 6],
+    /// One bitboard per color, indexed by `Piece::color_index`.
+    by_color: [Bitboard; 2],
     pub turn: Player,
+    pub(crate) castling: CastlingRights,
+    pub(crate) en_passant: Option<Position>,
+    pub(crate) halfmove_clock: u32,
+    pub(crate) fullmove_number: u32,
+    /// The Zobrist key of the pieces, castling rights and en-passant square,
+    /// kept up to date by every change to them. The side to move is only
+    /// added in `hash`, as `turn` can be set directly.
+    key: u64,
+    /// The Zobrist key of the pawns alone, for caching pawn structure scores.
+    pawn_key: u64,
+    /// The hashes of all positions before the current one, for the repetition
+    /// rule.
+    history: Vec<u64>,
+}
+
+/// Everything `Board::make_move` destroys, so `Board::unmake_move` can
+/// restore the previous position.
+#[derive(Debug, Clone)]
+pub struct Undo {
+    /// The piece taken by the move, `Piece::NONE` for quiet moves.
+    pub captured: Piece,
+    pub castling: CastlingRights,
+    pub en_passant: Option<Position>,
+    pub halfmove_clock: u32,
+    pub key: u64,
 }
 
 impl Default for Board {
     fn default() -> Self {
-        let mut pieces: [Piece; 64] = [Piece::NONE; 64];
-
-        pieces[0] = Piece::ROOK | Piece::WHITE;
-        pieces[1] = Piece::KNIGHT | Piece::WHITE;
-        pieces[2] = Piece::BISHOP | Piece::WHITE;
-        pieces[3] = Piece::QUEEN | Piece::WHITE;
-        pieces[4] = Piece::KING | Piece::WHITE;
-        pieces[5] = Piece::BISHOP | Piece::WHITE;
-        pieces[6] = Piece::KNIGHT | Piece::WHITE;
-        pieces[7] = Piece::ROOK | Piece::WHITE;
+        const BACK_RANK: [Piece; 8] = [
+            Piece::ROOK,
+            Piece::KNIGHT,
+            Piece::BISHOP,
+            Piece::QUEEN,
+            Piece::KING,
+            Piece::BISHOP,
+            Piece::KNIGHT,
+            Piece::ROOK,
+        ];
 
-        for i in 8..16 {
-            pieces[i] = Piece::PAWN | Piece::WHITE;
-        }
+        let mut board = Board::empty();
 
-        for i in 48..56 {
-            pieces[i] = Piece::PAWN | Piece::BLACK;
+        for (x, &piece) in BACK_RANK.iter().enumerate() {
+            let x = x as i8;
+            board.set(&Position::new(x, 0), piece | Piece::WHITE);
+            board.set(&Position::new(x, 1), Piece::PAWN | Piece::WHITE);
+            board.set(&Position::new(x, 6), Piece::PAWN | Piece::BLACK);
+            board.set(&Position::new(x, 7), piece | Piece::BLACK);
         }
 
-        pieces[56] = Piece::ROOK | Piece::BLACK;
-        pieces[57] = Piece::KNIGHT | Piece::BLACK;
-        pieces[58] = Piece::BISHOP | Piece::BLACK;
-        pieces[59] = Piece::QUEEN | Piece::BLACK;
-        pieces[60] = Piece::KING | Piece::BLACK;
-        pieces[61] = Piece::BISHOP | Piece::BLACK;
-        pieces[62] = Piece::KNIGHT | Piece::BLACK;
-        pieces[63] = Piece::ROOK | Piece::BLACK;
-
-        Board {
-            pieces,
-            turn: Player::White,
-        }
+        board.set_castling_rights(CastlingRights::all());
+        board
     }
 }
 
@@ -86,13 +149,7 @@ impl std::ops::Index<Position> for Board {
     type Output = Piece;
 
     fn index(&self, index: Position) -> &Self::Output {
-        &self.pieces[index.y as usize * 8 + index.x as usize]
-    }
-}
-
-impl std::ops::IndexMut<Position> for Board {
-    fn index_mut(&mut self, index: Position) -> &mut Self::Output {
-        &mut self.pieces[index.y as usize * 8 + index.x as usize]
+        &self.pieces[index.index()]
     }
 }
 
@@ -100,13 +157,7 @@ impl std::ops::Index<&Position> for Board {
     type Output = Piece;
 
     fn index(&self, index: &Position) -> &Self::Output {
-        &self.pieces[index.y as usize * 8 + index.x as usize]
-    }
-}
-
-impl std::ops::IndexMut<&Position> for Board {
-    fn index_mut(&mut self, index: &Position) -> &mut Self::Output {
-        &mut self.pieces[index.y as usize * 8 + index.x as usize]
+        &self.pieces[index.index()]
     }
 }
 
@@ -136,6 +187,49 @@ impl Board {
         format!("{}", self)
     }
 
+    /// Determines whether the game is still running, and if not, how it ended.
+    pub fn status(&self) -> GameStatus {
+        if self.generate_all_moves().is_empty() {
+            return if !self.in_check() {
+                GameStatus::Stalemate
+            } else if self.turn == Player::White {
+                GameStatus::BlackWins
+            } else {
+                GameStatus::WhiteWins
+            };
+        }
+
+        if self.has_insufficient_material() {
+            GameStatus::InsufficientMaterial
+        } else if self.repetitions() >= 3 {
+            GameStatus::ThreefoldRepetition
+        } else if self.halfmove_clock >= 100 {
+            GameStatus::FiftyMoveRule
+        } else {
+            GameStatus::Ongoing
+        }
+    }
+
+    #[wasm_bindgen(js_name = isGameOver)]
+    pub fn is_game_over(&self) -> bool {
+        self.status().is_over()
+    }
+
+    #[wasm_bindgen(js_name = inCheck)]
+    pub fn js_in_check(&self) -> bool {
+        self.in_check()
+    }
+
+    #[wasm_bindgen(js_name = fromFen)]
+    pub fn js_from_fen(fen: &str) -> Result<Board, JsValue> {
+        Board::from_fen(fen).map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    #[wasm_bindgen(js_name = toFen)]
+    pub fn js_to_fen(&self) -> String {
+        self.to_fen()
+    }
+
     #[wasm_bindgen(js_name = isTurnFor)]
     pub fn is_turn_for(&self, row: i8, col: i8) -> bool {
         let col = self.pieces[row as usize * 8 + col as usize].color();
@@ -151,241 +245,562 @@ impl Board {
         Board::default()
     }
 
-    pub fn generate_all_moves(&self) -> Vec<Move> {
-        let mut moves: Vec<Move> = Vec::new();
+    /// A board without any pieces and without castling rights, white to move.
+    pub fn empty() -> Board {
+        Board {
+            pieces: [Piece::NONE; 64],
+            by_kind: [0; 6],
+            by_color: [0; 2],
+            turn: Player::White,
+            castling: CastlingRights::empty(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            key: zobrist::castling(CastlingRights::empty()),
+            pawn_key: 0,
+            history: Vec::new(),
+        }
+    }
 
-        for i in 0..64 {
-            if self.is_turn_for(i as i8 / 8, i as i8 % 8) {
-                moves.append(&mut self.generate_moves_for(i.into()));
+    /// Puts `piece` on `pos`, replacing whatever stood there before.
+    /// `Piece::NONE` clears the square, and so does a piece without a kind or
+    /// without a color as it has no place in the bitboards.
+    pub fn set(&mut self, pos: &Position, piece: Piece) {
+        let square = pos.index();
+        let piece = if piece.kind() == Piece::NONE || piece.color() == Piece::NONE {
+            Piece::NONE
+        } else {
+            piece
+        };
+
+        let old = self.pieces[square];
+        if old != Piece::NONE {
+            self.by_kind[old.kind_index()] &= !bit(square);
+            self.by_color[old.color_index()] &= !bit(square);
+            self.key ^= zobrist::piece(old, square);
+            if old.contains(Piece::PAWN) {
+                self.pawn_key ^= zobrist::piece(old, square);
             }
         }
 
+        if piece != Piece::NONE {
+            self.by_kind[piece.kind_index()] |= bit(square);
+            self.by_color[piece.color_index()] |= bit(square);
+            self.key ^= zobrist::piece(piece, square);
+            if piece.contains(Piece::PAWN) {
+                self.pawn_key ^= zobrist::piece(piece, square);
+            }
+        }
+
+        self.pieces[square] = piece;
+    }
+
+    /// The squares occupied by pieces of the given kind and color.
+    pub fn pieces_of(&self, kind: Piece, color: Piece) -> Bitboard {
+        self.by_kind[kind.kind_index()] & self.by_color[color.color_index()]
+    }
+
+    /// The squares occupied by any piece of `color`.
+    pub fn color_bitboard(&self, color: Piece) -> Bitboard {
+        self.by_color[color.color_index()]
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.by_color[0] | self.by_color[1]
+    }
+
+    /// Generates all legal moves for the player whose turn it is.
+    pub fn generate_all_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(64);
+        self.generate_pseudo_legal_moves(self.turn.color(), !0, false, &mut moves);
+        moves.retain(|move_| self.is_legal(move_));
         moves
     }
 
+    /// Generates the legal captures, en passant included, and promotions of
+    /// the player whose turn it is.
+    pub fn generate_captures(&self) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(16);
+        self.generate_pseudo_legal_moves(self.turn.color(), !0, true, &mut moves);
+        moves.retain(|move_| self.is_legal(move_));
+        moves
+    }
+
+    /// Generates the legal moves of the piece on `idx`, which are none unless
+    /// it belongs to the player whose turn it is.
+    ///
+    /// Moves that would leave the moving side's king in check are filtered out.
     pub fn generate_moves_for(&self, idx: Position) -> Vec<Move> {
-        let piece = self[idx.clone()];
+        let mut moves = Vec::new();
 
-        match piece & !Piece::BLACK & !Piece::WHITE {
-            Piece::PAWN => self.generate_pawn_moves(&idx, piece),
-            Piece::KNIGHT => self.generate_knight_moves(&idx, piece),
-            Piece::BISHOP => self.generate_bishop_moves(idx, piece),
-            Piece::ROOK => self.generate_rook_moves(idx, piece),
-            Piece::QUEEN => self.generate_queen_moves(idx, piece),
-            Piece::KING => self.generate_king_moves(idx, piece),
-            _ => panic!("Invalid piece {:?}", piece),
+        let piece = self[&idx];
+        if piece != Piece::NONE {
+            self.generate_pseudo_legal_moves(piece.color(), bit(idx.index()), false, &mut moves);
+            moves.retain(|move_| self.is_legal(move_));
         }
+
+        moves
     }
 
-    fn generate_pawn_moves(&self, idx: &Position, piece: Piece) -> Vec<Move> {
-        const MOVES: [(i8, i8); 4] = [(0, 1), (0, 2), (1, 1), (-1, 1)];
+    /// Checks whether playing `move_` keeps the mover's own king safe. Moves
+    /// from an empty square or of a piece of the side not to move never are.
+    ///
+    /// Rather than playing the move, this only updates the occupancy and looks
+    /// for enemy pieces which would attack the king afterwards.
+    pub fn is_legal(&self, move_: &Move) -> bool {
+        let piece = self[&move_.start];
+        let color = piece.color();
+        if piece == Piece::NONE || color != self.turn.color() {
+            return false;
+        }
+        let (start, end) = (move_.start.index(), move_.end.index());
+
+        let captured = if move_.is_en_passant(self) {
+            bit(Position::new(move_.end.x, move_.start.y).index())
+        } else {
+            bit(end)
+        };
+        let occupied = (self.occupied() & !bit(start) & !captured) | bit(end);
+        let enemies = self.color_bitboard(color.opposite_color()) & !captured;
+
+        let king = if piece.kind() == Piece::KING {
+            end
+        } else {
+            match squares(self.pieces_of(Piece::KING, color)).next() {
+                Some(king) => king,
+                None => return true,
+            }
+        };
 
-        let mut moves = vec![];
+        self.attackers_to(king, occupied) & enemies == 0
+    }
 
-        for &(dx, dy) in &MOVES {
-            let end = if piece.contains(Piece::WHITE) {
-                Position {
-                    x: idx.x + dx,
-                    y: idx.y + dy,
-                }
-            } else {
-                Position {
-                    x: idx.x - dx,
-                    y: idx.y - dy,
-                }
-            };
+    pub fn castling_rights(&self) -> CastlingRights {
+        self.castling
+    }
 
-            if !end.is_valid() {
-                continue;
-            }
+    /// Removes the castling rights tied to `pos`, used whenever a piece moves
+    /// from or onto one of the king or rook home squares.
+    pub fn revoke_castling_rights(&mut self, pos: &Position) {
+        self.set_castling_rights(self.castling - CastlingRights::lost_by(pos));
+    }
 
-            if (dy == 1 || dy == 2) && dx.abs() != 1 && self[end.clone()] != Piece::NONE {
-                continue;
-            }
+    pub fn set_castling_rights(&mut self, rights: CastlingRights) {
+        self.key ^= zobrist::castling(self.castling) ^ zobrist::castling(rights);
+        self.castling = rights;
+    }
 
-            if dy == 2
-                && ((piece.color() == Piece::WHITE && idx.y != 1)
-                    || piece.color() == Piece::BLACK && idx.y != 6)
-            {
-                continue;
-            }
+    /// The square a pawn skipped with a double push on the last move, which an
+    /// enemy pawn may capture onto en passant.
+    pub fn en_passant(&self) -> Option<Position> {
+        self.en_passant.clone()
+    }
 
-            if dx.abs() == 1 && !self[&end].contains(piece.opposite_color()) {
-                continue;
-            }
+    /// Sets the en-passant square, but only if a pawn of the side to move
+    /// attacks it. Otherwise the position is the same as without a double
+    /// push, both for the hash and for the repetition rule.
+    pub fn set_en_passant(&mut self, pos: Option<Position>) {
+        let color = self.turn.color();
+        let pawns = self.pieces_of(Piece::PAWN, color);
+        let pos = pos.filter(|pos| {
+            pawn_attacks(color.opposite_color().color_index(), pos.index()) & pawns != 0
+        });
+
+        if let Some(old) = &self.en_passant {
+            self.key ^= zobrist::en_passant(old.x);
+        }
+        if let Some(new) = &pos {
+            self.key ^= zobrist::en_passant(new.x);
+        }
+        self.en_passant = pos;
+    }
 
-            moves.push(Move {
-                start: idx.clone(),
-                end: end.clone(),
-            });
+    /// The Zobrist hash of the position, equal for positions with the same
+    /// pieces, side to move, castling rights and en-passant square.
+    pub fn hash(&self) -> u64 {
+        match self.turn {
+            Player::White => self.key,
+            Player::Black => self.key ^ zobrist::black_to_move(),
         }
+    }
 
-        moves
+    /// The Zobrist hash of the pawns of both sides alone.
+    pub fn pawn_hash(&self) -> u64 {
+        self.pawn_key
     }
 
-    fn generate_knight_moves(&self, idx: &Position, piece: Piece) -> Vec<Move> {
-        const MOVES: [(i8, i8); 8] = [
-            (1, 2),
-            (2, 1),
-            (-1, 2),
-            (-2, 1),
-            (1, -2),
-            (2, -1),
-            (-1, -2),
-            (-2, -1),
-        ];
+    /// Computes the pawn hash from scratch. Only useful for verifying
+    /// `pawn_hash`.
+    pub fn compute_pawn_hash(&self) -> u64 {
+        squares(self.by_kind[Piece::PAWN.kind_index()]).fold(0, |hash, square| {
+            hash ^ zobrist::piece(self.pieces[square], square)
+        })
+    }
 
-        let mut moves = Vec::<Move>::new();
+    /// Computes the hash from scratch instead of using the incrementally
+    /// updated key. Only useful for verifying `hash`.
+    pub fn compute_hash(&self) -> u64 {
+        let mut hash = zobrist::castling(self.castling);
 
-        for &(x, y) in &MOVES {
-            let pos = Position {
-                x: idx.x + x,
-                y: idx.y + y,
-            };
+        for square in squares(self.occupied()) {
+            hash ^= zobrist::piece(self.pieces[square], square);
+        }
+        if let Some(pos) = &self.en_passant {
+            hash ^= zobrist::en_passant(pos.x);
+        }
+        if self.turn == Player::Black {
+            hash ^= zobrist::black_to_move();
+        }
 
-            if pos.is_valid() {
-                let end_piece = self[pos.clone()];
-                if end_piece == Piece::NONE || end_piece.contains(piece.opposite_color()) {
-                    moves.push(Move {
-                        start: idx.clone(),
-                        end: pos.clone(),
-                    });
-                }
-            }
+        hash
+    }
+
+    /// Returns whether the player to move is currently in check.
+    pub fn in_check(&self) -> bool {
+        let color = self.turn.color();
+        match self.king_position(color) {
+            Some(king) => self.is_square_attacked(king, color.opposite_color()),
+            None => false,
         }
+    }
 
-        moves
+    pub fn king_position(&self, color: Piece) -> Option<Position> {
+        squares(self.pieces_of(Piece::KING, color))
+            .next()
+            .map(Position::from)
     }
 
-    fn generate_bishop_moves(&self, idx: Position, piece: Piece) -> Vec<Move> {
-        const MOVES: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+    /// Checks whether any piece of `color` attacks the square `pos`.
+    pub fn is_square_attacked(&self, pos: Position, color: Piece) -> bool {
+        self.attackers_to(pos.index(), self.occupied()) & self.color_bitboard(color) != 0
+    }
 
-        let mut moves = Vec::<Move>::new();
+    /// All pieces of either color attacking `square`, with sliders being
+    /// blocked by the pieces in `occupied`.
+    pub fn attackers_to(&self, square: usize, occupied: Bitboard) -> Bitboard {
+        let [pawns, knights, bishops, rooks, queens, kings] = self.by_kind;
+
+        // A pawn attacks the square exactly if a pawn of the other color
+        // standing on the square would attack the pawn
+        (pawn_attacks(0, square) & pawns & self.by_color[1])
+            | (pawn_attacks(1, square) & pawns & self.by_color[0])
+            | (knight_attacks(square) & knights)
+            | (king_attacks(square) & kings)
+            | (bishop_attacks(square, occupied) & (bishops | queens))
+            | (rook_attacks(square, occupied) & (rooks | queens))
+    }
 
-        for &(x, y) in &MOVES {
-            let mut pos = Position {
-                x: idx.x + x,
-                y: idx.y + y,
-            };
+    /// Appends the pseudo-legal moves of the `color` pieces standing on one of
+    /// the `from` squares to `moves`. With `captures_only`, only captures and
+    /// promotions are generated.
+    fn generate_pseudo_legal_moves(
+        &self,
+        color: Piece,
+        from: Bitboard,
+        captures_only: bool,
+        moves: &mut Vec<Move>,
+    ) {
+        let own = self.color_bitboard(color);
+        let enemies = self.color_bitboard(color.opposite_color());
+        let occupied = own | enemies;
+        let targets = if captures_only { enemies } else { !own };
+
+        self.generate_pawn_moves(color, from, captures_only, moves);
+
+        for start in squares(self.pieces_of(Piece::KNIGHT, color) & from) {
+            push_moves(moves, start, knight_attacks(start) & targets);
+        }
+
+        for start in squares(self.pieces_of(Piece::BISHOP, color) & from) {
+            push_moves(moves, start, bishop_attacks(start, occupied) & targets);
+        }
+
+        for start in squares(self.pieces_of(Piece::ROOK, color) & from) {
+            push_moves(moves, start, rook_attacks(start, occupied) & targets);
+        }
+
+        for start in squares(self.pieces_of(Piece::QUEEN, color) & from) {
+            push_moves(moves, start, queen_attacks(start, occupied) & targets);
+        }
+
+        for start in squares(self.pieces_of(Piece::KING, color) & from) {
+            push_moves(moves, start, king_attacks(start) & targets);
+            if !captures_only {
+                moves.extend(self.generate_castling_moves(&start.into(), Piece::KING | color));
+            }
+        }
+    }
+
+    fn generate_pawn_moves(
+        &self,
+        color: Piece,
+        from: Bitboard,
+        captures_only: bool,
+        moves: &mut Vec<Move>,
+    ) {
+        let us = color.color_index();
+        let occupied = self.occupied();
+        let en_passant = self.en_passant.as_ref().map_or(0, |pos| bit(pos.index()));
+        let targets = self.color_bitboard(color.opposite_color()) | en_passant;
+        let push_targets = if captures_only { RANK_1 | RANK_8 } else { !0 };
+
+        let (forward, double_push_row): (isize, usize) = if us == 0 { (8, 1) } else { (-8, 6) };
+
+        // Pawns can never stand on the outer ranks, so pushing never leaves the board
+        for start in squares(self.pieces_of(Piece::PAWN, color) & from & !(RANK_1 | RANK_8)) {
+            let mut ends = pawn_attacks(us, start) & targets;
+
+            let one = start.wrapping_add_signed(forward);
+            if occupied & bit(one) == 0 {
+                ends |= bit(one) & push_targets;
+
+                let two = one.wrapping_add_signed(forward);
+                if start / 8 == double_push_row && occupied & bit(two) == 0 {
+                    ends |= bit(two) & push_targets;
+                }
+            }
 
-            while pos.is_valid() {
-                let end_piece = self[pos.clone()];
-                if end_piece == Piece::NONE {
-                    // There is no piece in the way, therefore we can continue to search
-                    moves.push(Move {
-                        start: idx.clone(),
-                        end: pos.clone(),
-                    });
-
-                    pos.x += x;
-                    pos.y += y;
-                } else if end_piece.contains(piece.opposite_color()) {
-                    moves.push(Move {
-                        start: idx.clone(),
-                        end: pos.clone(),
-                    });
-                    break;
+            for end in squares(ends) {
+                if end / 8 == 0 || end / 8 == 7 {
+                    for promotion in Piece::PROMOTIONS {
+                        moves.push(Move::with_promotion(start.into(), end.into(), promotion));
+                    }
                 } else {
-                    break;
+                    moves.push(Move::new(start.into(), end.into()));
                 }
             }
         }
-
-        moves
     }
 
-    fn generate_rook_moves(&self, idx: Position, piece: Piece) -> Vec<Move> {
-        const MOVES: [(i8, i8); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+    fn generate_castling_moves(&self, idx: &Position, piece: Piece) -> Vec<Move> {
+        let (row, kingside, queenside) = if piece.color() == Piece::WHITE {
+            (
+                0,
+                CastlingRights::WHITE_KINGSIDE,
+                CastlingRights::WHITE_QUEENSIDE,
+            )
+        } else {
+            (
+                7,
+                CastlingRights::BLACK_KINGSIDE,
+                CastlingRights::BLACK_QUEENSIDE,
+            )
+        };
 
         let mut moves = Vec::<Move>::new();
 
-        for &(x, y) in &MOVES {
-            let mut pos = Position {
-                x: idx.x + x,
-                y: idx.y + y,
-            };
+        if idx.x != 4 || idx.y != row {
+            return moves;
+        }
 
-            while pos.is_valid() {
-                let end_piece = self[pos.clone()];
-                if end_piece == Piece::NONE {
-                    // There is no piece in the way, therefore we can continue to search
-                    moves.push(Move {
-                        start: idx.clone(),
-                        end: pos.clone(),
-                    });
-
-                    pos.x += x;
-                    pos.y += y;
-                } else if end_piece.contains(piece.opposite_color()) {
-                    moves.push(Move {
-                        start: idx.clone(),
-                        end: pos.clone(),
-                    });
-                    break;
-                } else {
-                    break;
-                }
-            }
+        let enemy = piece.opposite_color();
+        let rook = Piece::ROOK | piece.color();
+        let empty = |cols: &[i8]| {
+            cols.iter()
+                .all(|&x| self[Position::new(x, row)] == Piece::NONE)
+        };
+        let safe = |cols: &[i8]| {
+            cols.iter()
+                .all(|&x| !self.is_square_attacked(Position::new(x, row), enemy))
+        };
+
+        if self.castling.contains(kingside)
+            && self[Position::new(7, row)] == rook
+            && empty(&[5, 6])
+            && safe(&[4, 5, 6])
+        {
+            moves.push(Move::new(idx.clone(), Position::new(6, row)));
+        }
+
+        if self.castling.contains(queenside)
+            && self[Position::new(0, row)] == rook
+            && empty(&[1, 2, 3])
+            && safe(&[4, 3, 2])
+        {
+            moves.push(Move::new(idx.clone(), Position::new(2, row)));
         }
 
         moves
     }
 
-    fn generate_queen_moves(&self, idx: Position, piece: Piece) -> Vec<Move> {
-        let mut moves = self.generate_bishop_moves(idx.clone(), piece);
-        moves.extend(self.generate_rook_moves(idx, piece));
+    pub fn positions(&self) -> Vec<Position> {
+        squares(self.occupied()).map(Position::from).collect()
+    }
 
-        moves
+    /// Number of halfmoves since the last capture or pawn move.
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
     }
 
-    fn generate_king_moves(&self, idx: Position, piece: Piece) -> Vec<Move> {
-        const MOVES: [(i8, i8); 8] = [
-            (1, 1),
-            (1, -1),
-            (-1, 1),
-            (-1, -1),
-            (1, 0),
-            (0, 1),
-            (-1, 0),
-            (0, -1),
-        ];
+    /// The number of the current full move, starting at 1 and incremented
+    /// after every move of black.
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
 
-        let mut moves = Vec::<Move>::new();
-        for (x, y) in &MOVES {
-            let pos = Position {
-                x: idx.x + x,
-                y: idx.y + y,
-            };
+    /// Bookkeeping for the move counters and the repetition rule, called before
+    /// a move is applied to the board.
+    pub fn record_move(&mut self, irreversible: bool) {
+        if self.turn == Player::Black {
+            self.fullmove_number += 1;
+        }
 
-            if pos.is_valid() {
-                let end_piece = self[pos.clone()];
+        if irreversible {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
 
-                if end_piece == Piece::NONE || end_piece.contains(piece.opposite_color()) {
-                    moves.push(Move {
-                        start: idx.clone(),
-                        end: pos.clone(),
-                    });
-                }
-            }
+        let hash = self.hash();
+        self.history.push(hash);
+    }
+
+    /// How often the current position has occurred, including right now.
+    pub fn repetitions(&self) -> usize {
+        let hash = self.hash();
+
+        // Positions before the last capture or pawn move can never come back
+        let reversible = self.history.len().min(self.halfmove_clock as usize);
+        let recent = &self.history[self.history.len() - reversible..];
+
+        1 + recent.iter().filter(|&&other| other == hash).count()
+    }
+
+    /// Plays `move_` on the board and returns what is needed to take it back
+    /// again with `unmake_move`.
+    ///
+    /// The move has to be legal, or at least pseudo-legal, in this position.
+    pub fn make_move(&mut self, move_: &Move) -> Undo {
+        let piece = self[&move_.start];
+
+        let captured_on = if move_.is_en_passant(self) {
+            // The captured pawn sits beside the start square, not on the target square
+            Position::new(move_.end.x, move_.start.y)
+        } else {
+            move_.end.clone()
+        };
+        let captured = self[&captured_on];
+
+        let undo = Undo {
+            captured,
+            castling: self.castling,
+            en_passant: self.en_passant.clone(),
+            halfmove_clock: self.halfmove_clock,
+            key: self.key,
+        };
+
+        self.record_move(piece.contains(Piece::PAWN) || captured != Piece::NONE);
+        self.set(&captured_on, Piece::NONE);
+
+        if move_.is_castling(self) {
+            // The rook jumps over the king onto the square the king passed
+            let (rook_start, rook_end) = castling_rook_squares(move_);
+            self.set(&rook_end, self[&rook_start]);
+            self.set(&rook_start, Piece::NONE);
         }
 
-        moves
+        self.revoke_castling_rights(&move_.start);
+        self.revoke_castling_rights(&move_.end);
+
+        let placed = match move_.promotion {
+            Some(promotion) => promotion | piece.color(),
+            None => piece,
+        };
+        self.set(&move_.end, placed);
+        self.set(&move_.start, Piece::NONE);
+        self.turn = self.turn.next();
+
+        // Set only now, as it depends on the pawns of the side to move
+        if piece.contains(Piece::PAWN) && (move_.end.y - move_.start.y).abs() == 2 {
+            let skipped = Position::new(move_.start.x, (move_.start.y + move_.end.y) / 2);
+            self.set_en_passant(Some(skipped));
+        } else {
+            self.set_en_passant(None);
+        }
+
+        undo
     }
 
-    pub fn positions(&self) -> Vec<Position> {
-        let mut positions = Vec::<Position>::new();
+    /// Takes back `move_`, which must be the last move made with `make_move`.
+    pub fn unmake_move(&mut self, move_: &Move, undo: Undo) {
+        self.turn = self.turn.next();
+        if self.turn == Player::Black {
+            self.fullmove_number -= 1;
+        }
+
+        let placed = self[&move_.end];
+        let piece = match move_.promotion {
+            Some(_) => Piece::PAWN | placed.color(),
+            None => placed,
+        };
+        self.set(&move_.end, Piece::NONE);
+        self.set(&move_.start, piece);
+
+        if piece.contains(Piece::KING) && (move_.end.x - move_.start.x).abs() == 2 {
+            let (rook_start, rook_end) = castling_rook_squares(move_);
+            self.set(&rook_start, self[&rook_end]);
+            self.set(&rook_end, Piece::NONE);
+        }
+
+        if undo.captured != Piece::NONE {
+            let en_passant =
+                piece.contains(Piece::PAWN) && undo.en_passant == Some(move_.end.clone());
+            let captured_on = if en_passant {
+                Position::new(move_.end.x, move_.start.y)
+            } else {
+                move_.end.clone()
+            };
+            self.set(&captured_on, undo.captured);
+        }
+
+        self.castling = undo.castling;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.key = undo.key;
+        self.history.pop();
+    }
 
-        for i in 0..64 {
-            let pos: Position = i.into();
-            if self[pos.clone()] != Piece::NONE {
-                positions.push(pos);
+    /// Checks whether neither side has enough material left to ever mate,
+    /// i.e. only kings and at most a single minor piece or bishops which are
+    /// all on the same square color.
+    pub fn has_insufficient_material(&self) -> bool {
+        let mut knights = 0;
+        let mut bishops = 0;
+        let mut bishop_colors = [false; 2];
+
+        for (i, piece) in self.pieces.iter().enumerate() {
+            match piece.kind() {
+                Piece::NONE | Piece::KING => {}
+                Piece::KNIGHT => knights += 1,
+                Piece::BISHOP => {
+                    bishops += 1;
+                    bishop_colors[(i / 8 + i % 8) % 2] = true;
+                }
+                _ => return false,
             }
         }
 
-        positions
+        let bishops_on_one_color = !(bishop_colors[0] && bishop_colors[1]);
+        knights + bishops <= 1 || (knights == 0 && bishops_on_one_color)
     }
+}
 
-    pub fn is_game_over(&self) -> bool {
-        false
+/// The start and end square of the rook when `move_` is a castling move.
+fn castling_rook_squares(move_: &Move) -> (Position, Position) {
+    let (rook_start, rook_end) = if move_.end.x > move_.start.x {
+        (7, 5)
+    } else {
+        (0, 3)
+    };
+
+    (
+        Position::new(rook_start, move_.start.y),
+        Position::new(rook_end, move_.start.y),
+    )
+}
+
+fn push_moves(moves: &mut Vec<Move>, start: usize, ends: Bitboard) {
+    for end in squares(ends) {
+        moves.push(Move::new(start.into(), end.into()));
     }
 }
@@ -0,0 +1,215 @@
+//! Static evaluation of positions in centipawns.
+//!
+//! Every piece is worth its material plus a bonus from a piece-square table
+//! for the square it stands on. Both come in a middlegame and an endgame
+//! flavour, which are blended by the amount of material left on the board, so
+//! e.g. the king is kept safe while the queens are on and marches to the
+//! center once they are gone.
+//!
+//! The tables are written from white's point of view with rank 8 at the top,
+//! the way a board is usually printed.
+//!
+//! On top of that come the pawn structure and the king safety, see the
+//! `pawns` and `king_safety` modules.
+
+use crate::bitboard::squares;
+use crate::board::Board;
+use crate::king_safety::evaluate_king_safety;
+use crate::pawns::{self, PawnEntry, PawnTable};
+use crate::piece::Piece;
+
+/// Material values in the middlegame and the endgame, by kind index.
+const MIDGAME_VALUES: [i32; 6] = [82, 337, 365, 477, 1025, 0];
+const ENDGAME_VALUES: [i32; 6] = [94, 281, 297, 512, 936, 0];
+
+/// How much each piece kind counts towards the middlegame phase.
+const PHASE_WEIGHTS: [i32; 6] = [0, 1, 1, 2, 4, 0];
+/// The phase of the starting position.
+const MAX_PHASE: i32 = 24;
+
+#[rustfmt::skip]
+const PAWN_MIDGAME: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     10,  10,  20,  30,  30,  20,  10,  10,
+      5,   5,  10,  25,  25,  10,   5,   5,
+      0,   0,   0,  20,  20,   0,   0,   0,
+      5,  -5, -10,   0,   0, -10,  -5,   5,
+      5,  10,  10, -20, -20,  10,  10,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const PAWN_ENDGAME: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     80,  80,  80,  80,  80,  80,  80,  80,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     30,  30,  30,  30,  30,  30,  30,  30,
+     15,  15,  15,  15,  15,  15,  15,  15,
+      5,   5,   5,   5,   5,   5,   5,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const KNIGHT: [i32; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+const BISHOP: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const ROOK_MIDGAME: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+      5,  10,  10,  10,  10,  10,  10,   5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+      0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const ROOK_ENDGAME: [i32; 64] = [
+      5,   5,   5,   5,   5,   5,   5,   5,
+     10,  10,  10,  10,  10,  10,  10,  10,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const QUEEN: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const KING_MIDGAME: [i32; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+#[rustfmt::skip]
+const KING_ENDGAME: [i32; 64] = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+/// Piece-square tables by kind index.
+const MIDGAME_TABLES: [&[i32; 64]; 6] = [
+    &PAWN_MIDGAME,
+    &KNIGHT,
+    &BISHOP,
+    &ROOK_MIDGAME,
+    &QUEEN,
+    &KING_MIDGAME,
+];
+const ENDGAME_TABLES: [&[i32; 64]; 6] = [
+    &PAWN_ENDGAME,
+    &KNIGHT,
+    &BISHOP,
+    &ROOK_ENDGAME,
+    &QUEEN,
+    &KING_ENDGAME,
+];
+
+/// The middlegame value of a piece in centipawns. The king and an empty
+/// square count as zero.
+pub fn piece_value(piece: Piece) -> i32 {
+    if piece == Piece::NONE {
+        0
+    } else {
+        MIDGAME_VALUES[piece.kind_index()]
+    }
+}
+
+/// Evaluates the position in centipawns from the point of view of the side
+/// to move. Checkmate and draws are not detected here.
+pub fn evaluate(board: &Board) -> i32 {
+    evaluate_with(board, &pawns::evaluate_pawns(board))
+}
+
+/// Like `evaluate`, but looks the pawn structure up in `pawn_table`.
+pub fn evaluate_cached(board: &Board, pawn_table: &mut PawnTable) -> i32 {
+    evaluate_with(board, &pawn_table.probe(board))
+}
+
+fn evaluate_with(board: &Board, pawns: &PawnEntry) -> i32 {
+    let mut midgame = 0;
+    let mut endgame = 0;
+    let mut phase = 0;
+
+    for color in [Piece::WHITE, Piece::BLACK] {
+        let sign = if color == board.turn.color() { 1 } else { -1 };
+
+        for kind in Piece::KINDS {
+            let kind_index = kind.kind_index();
+
+            for square in squares(board.pieces_of(kind, color)) {
+                // The tables start with rank 8, so white's squares are flipped
+                let index = if color == Piece::WHITE {
+                    square ^ 56
+                } else {
+                    square
+                };
+
+                midgame += sign * (MIDGAME_VALUES[kind_index] + MIDGAME_TABLES[kind_index][index]);
+                endgame += sign * (ENDGAME_VALUES[kind_index] + ENDGAME_TABLES[kind_index][index]);
+                phase += PHASE_WEIGHTS[kind_index];
+            }
+        }
+    }
+
+    // These terms are from white's point of view
+    let sign = if board.turn.color() == Piece::WHITE {
+        1
+    } else {
+        -1
+    };
+    midgame += sign * (pawns.midgame + evaluate_king_safety(board));
+    endgame += sign * (pawns.endgame + pawns::passed_pawn_kings(board, pawns.passed));
+
+    // Promotions can push the phase past the start
+    let phase = phase.min(MAX_PHASE);
+    (midgame * phase + endgame * (MAX_PHASE - phase)) / MAX_PHASE
+}
@@ -0,0 +1,213 @@
+use crate::bitboard::{squares, RANK_1, RANK_8};
+use crate::board::{Board, CastlingRights, Player};
+use crate::piece::Piece;
+use crate::pos::Position;
+
+use std::fmt;
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    /// A FEN needs at least the placement, side to move, castling and en-passant
+    /// fields, the two move counters are optional.
+    FieldCount(usize),
+    RankCount(usize),
+    /// The rank, counted from the top as in the FEN string, does not describe
+    /// exactly eight squares.
+    RankLength(usize),
+    InvalidPiece(char),
+    InvalidSideToMove(String),
+    InvalidCastling(String),
+    InvalidEnPassant(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+    /// The fields can be read, but describe a position no game can reach:
+    /// a side without exactly one king, or a pawn on the first or last rank.
+    ImpossiblePosition(String),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            FenError::FieldCount(n) => write!(f, "expected 4 to 6 fields, found {}", n),
+            FenError::RankCount(n) => write!(f, "expected 8 ranks, found {}", n),
+            FenError::RankLength(rank) => write!(f, "rank {} does not have 8 squares", rank),
+            FenError::InvalidPiece(c) => write!(f, "invalid piece '{}'", c),
+            FenError::InvalidSideToMove(s) => write!(f, "invalid side to move '{}'", s),
+            FenError::InvalidCastling(s) => write!(f, "invalid castling rights '{}'", s),
+            FenError::InvalidEnPassant(s) => write!(f, "invalid en-passant square '{}'", s),
+            FenError::InvalidHalfmoveClock(s) => write!(f, "invalid halfmove clock '{}'", s),
+            FenError::InvalidFullmoveNumber(s) => write!(f, "invalid fullmove number '{}'", s),
+            FenError::ImpossiblePosition(s) => write!(f, "impossible position, {}", s),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+impl Board {
+    /// Sets up a board from a position in Forsyth-Edwards Notation.
+    pub fn from_fen(fen: &str) -> Result<Board, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() < 4 || fields.len() > 6 {
+            return Err(FenError::FieldCount(fields.len()));
+        }
+
+        let mut board = Board::empty();
+
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError::RankCount(ranks.len()));
+        }
+
+        for (i, rank) in ranks.iter().enumerate() {
+            let y = 7 - i as i8;
+            let mut x = 0;
+
+            for c in rank.chars() {
+                if let Some(empty) = c.to_digit(10).filter(|d| (1..=8).contains(d)) {
+                    x += empty as i8;
+                } else {
+                    let piece = Piece::from_char(c).ok_or(FenError::InvalidPiece(c))?;
+                    if x >= 8 {
+                        return Err(FenError::RankLength(i + 1));
+                    }
+                    board.set(&Position::new(x, y), piece);
+                    x += 1;
+                }
+            }
+
+            if x != 8 {
+                return Err(FenError::RankLength(i + 1));
+            }
+        }
+
+        board.turn = match fields[1] {
+            "w" => Player::White,
+            "b" => Player::Black,
+            other => return Err(FenError::InvalidSideToMove(other.to_string())),
+        };
+
+        if fields[2] != "-" {
+            for c in fields[2].chars() {
+                let right = match c {
+                    'K' => CastlingRights::WHITE_KINGSIDE,
+                    'Q' => CastlingRights::WHITE_QUEENSIDE,
+                    'k' => CastlingRights::BLACK_KINGSIDE,
+                    'q' => CastlingRights::BLACK_QUEENSIDE,
+                    _ => return Err(FenError::InvalidCastling(fields[2].to_string())),
+                };
+                board.set_castling_rights(board.castling | right);
+            }
+        }
+
+        if fields[3] != "-" {
+            // The square the enemy pawn skipped, with that pawn right in front of it
+            let (rank, pawn_rank, pawn) = match board.turn {
+                Player::White => (5, 4, Piece::PAWN | Piece::BLACK),
+                Player::Black => (2, 3, Piece::PAWN | Piece::WHITE),
+            };
+            let square = fields[3]
+                .parse::<Position>()
+                .ok()
+                .filter(|pos| pos.y == rank && board[Position::new(pos.x, pawn_rank)] == pawn)
+                .ok_or_else(|| FenError::InvalidEnPassant(fields[3].to_string()))?;
+            board.set_en_passant(Some(square));
+        }
+
+        if let Some(clock) = fields.get(4) {
+            board.halfmove_clock = clock
+                .parse()
+                .map_err(|_| FenError::InvalidHalfmoveClock(clock.to_string()))?;
+        }
+
+        if let Some(number) = fields.get(5) {
+            board.fullmove_number = number
+                .parse()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| FenError::InvalidFullmoveNumber(number.to_string()))?;
+        }
+
+        for player in [Player::White, Player::Black] {
+            let kings = board.pieces_of(Piece::KING, player.color()).count_ones();
+            if kings != 1 {
+                return Err(FenError::ImpossiblePosition(format!(
+                    "{:?} has {} kings",
+                    player, kings
+                )));
+            }
+        }
+
+        let pawns =
+            board.pieces_of(Piece::PAWN, Piece::WHITE) | board.pieces_of(Piece::PAWN, Piece::BLACK);
+        if let Some(square) = squares(pawns & (RANK_1 | RANK_8)).next() {
+            return Err(FenError::ImpossiblePosition(format!(
+                "pawn on {}",
+                Position::from(square).to_algebraic()
+            )));
+        }
+
+        Ok(board)
+    }
+
+    /// Describes the position in Forsyth-Edwards Notation.
+    pub fn to_fen(&self) -> String {
+        let mut placement = String::new();
+
+        for y in (0..8).rev() {
+            let mut empty = 0;
+            for x in 0..8 {
+                let piece = self[Position::new(x, y)];
+                if piece == Piece::NONE {
+                    empty += 1;
+                    continue;
+                }
+
+                if empty > 0 {
+                    placement.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                placement.push_str(&piece.to_string());
+            }
+
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+            }
+            if y > 0 {
+                placement.push('/');
+            }
+        }
+
+        let turn = match self.turn {
+            Player::White => "w",
+            Player::Black => "b",
+        };
+
+        let mut castling = String::new();
+        for (right, c) in [
+            (CastlingRights::WHITE_KINGSIDE, 'K'),
+            (CastlingRights::WHITE_QUEENSIDE, 'Q'),
+            (CastlingRights::BLACK_KINGSIDE, 'k'),
+            (CastlingRights::BLACK_QUEENSIDE, 'q'),
+        ] {
+            if self.castling.contains(right) {
+                castling.push(c);
+            }
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        let en_passant = self
+            .en_passant
+            .as_ref()
+            .map_or_else(|| "-".to_string(), Position::to_algebraic);
+
+        format!(
+            "{} {} {} {} {} {}",
+            placement, turn, castling, en_passant, self.halfmove_clock, self.fullmove_number
+        )
+    }
+}
@@ -0,0 +1,109 @@
+//! King safety evaluation.
+//!
+//! A king is safe behind a shield of its own pawns and unsafe on open files
+//! or when several enemy pieces attack the squares around it. The danger from
+//! attackers grows quadratically, as one attacker is rarely a threat while a
+//! few together often are. All of this only matters while there is enough
+//! material left to mate, so the score is a middlegame term and fades out
+//! with the phase.
+
+use crate::bitboard::{
+    bishop_attacks, bit, king_attacks, knight_attacks, queen_attacks, rook_attacks, squares,
+    Bitboard, FILE_A,
+};
+use crate::board::Board;
+use crate::pawns::ranks_ahead;
+use crate::piece::Piece;
+
+/// Bonus for a shield pawn by how many ranks it stands in front of the king.
+const SHELTER: [i32; 4] = [0, 20, 10, 5];
+/// Penalty for a file at or next to the king without own pawns in front of
+/// it, and extra penalty if there are no enemy pawns on it either.
+const HALF_OPEN_FILE: i32 = -20;
+const OPEN_FILE: i32 = -10;
+
+/// How dangerous each piece kind attacking the king zone is, by kind index.
+const ATTACK_WEIGHTS: [i32; 6] = [0, 2, 2, 3, 5, 0];
+/// The most a king can lose to attackers.
+const MAX_DANGER: i32 = 500;
+
+/// Looks up the squares a piece attacks from a square given the occupancy.
+type Attacks = fn(usize, Bitboard) -> Bitboard;
+
+/// The king safety of both sides as a middlegame score from white's point
+/// of view.
+pub fn evaluate_king_safety(board: &Board) -> i32 {
+    king_safety(board, 0, Piece::WHITE) - king_safety(board, 1, Piece::BLACK)
+}
+
+fn king_safety(board: &Board, us: usize, color: Piece) -> i32 {
+    let Some(king) = squares(board.pieces_of(Piece::KING, color)).next() else {
+        return 0;
+    };
+    let enemy = color.opposite_color();
+
+    shelter(board, us, color, king) - danger(board, enemy, king)
+}
+
+/// Rewards pawns in front of the king and punishes open files around it.
+fn shelter(board: &Board, us: usize, color: Piece, king: usize) -> i32 {
+    let (x, y) = (king % 8, king / 8);
+    let own = board.pieces_of(Piece::PAWN, color) & ranks_ahead(us, y);
+    let enemies = board.pieces_of(Piece::PAWN, color.opposite_color());
+    let mut score = 0;
+
+    for file in (x.saturating_sub(1)..=(x + 1).min(7)).map(|x| FILE_A << x) {
+        let shield = own & file;
+        if shield == 0 {
+            score += HALF_OPEN_FILE;
+            if enemies & file == 0 {
+                score += OPEN_FILE;
+            }
+            continue;
+        }
+
+        // The shield pawn closest to the king
+        let pawn = if us == 0 {
+            shield.trailing_zeros()
+        } else {
+            63 - shield.leading_zeros()
+        } as usize;
+        let ranks = (pawn / 8).abs_diff(y);
+        score += SHELTER.get(ranks).copied().unwrap_or(0);
+    }
+
+    score
+}
+
+/// The danger from pieces of `enemy` attacking the squares around the king.
+fn danger(board: &Board, enemy: Piece, king: usize) -> i32 {
+    let zone = king_attacks(king) | bit(king);
+    let occupied = board.occupied();
+    let mut attackers = 0;
+    let mut units = 0;
+
+    let kinds: [(Piece, Attacks); 4] = [
+        (Piece::KNIGHT, |square, _| knight_attacks(square)),
+        (Piece::BISHOP, bishop_attacks),
+        (Piece::ROOK, rook_attacks),
+        (Piece::QUEEN, queen_attacks),
+    ];
+
+    for (kind, attacks) in kinds {
+        for square in squares(board.pieces_of(kind, enemy)) {
+            let attacks = attacks(square, occupied) & zone;
+
+            if attacks != 0 {
+                attackers += 1;
+                units += ATTACK_WEIGHTS[kind.kind_index()] * attacks.count_ones() as i32;
+            }
+        }
+    }
+
+    // A lone attacker can rarely do harm
+    if attackers < 2 {
+        0
+    } else {
+        (units * units / 2).min(MAX_DANGER)
+    }
+}
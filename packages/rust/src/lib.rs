@@ -1,12 +1,23 @@
 #![allow(clippy::unused_unit)]
 #![allow(clippy::inherent_to_string)]
 
-mod ai;
-mod board;
-mod moves;
-mod piece;
-mod pos;
-mod utils;
+pub mod ai;
+pub mod bitboard;
+pub mod board;
+pub mod eval;
+pub mod fen;
+pub mod king_safety;
+pub mod moves;
+pub mod pawns;
+pub mod perft;
+pub mod pgn;
+pub mod piece;
+pub mod pos;
+pub mod san;
+pub mod status;
+pub mod tt;
+pub mod utils;
+pub mod zobrist;
 
 use pos::Position;
 use wasm_bindgen::prelude::*;
@@ -1,19 +1,56 @@
 use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::JsValue;
 
-use crate::{board::Board, piece::Piece, pos::Position};
+use crate::{
+    board::Board,
+    piece::Piece,
+    pos::{ParsePositionError, Position},
+};
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UciMoveError {
+    /// A move is written as two squares, optionally followed by a promotion.
+    InvalidLength(String),
+    InvalidSquare(ParsePositionError),
+    InvalidPromotion(char),
+}
+
+impl fmt::Display for UciMoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            UciMoveError::InvalidLength(s) => write!(f, "invalid move '{}'", s),
+            UciMoveError::InvalidSquare(err) => write!(f, "{}", err),
+            UciMoveError::InvalidPromotion(c) => write!(f, "invalid promotion piece '{}'", c),
+        }
+    }
+}
+
+impl std::error::Error for UciMoveError {}
+
+impl From<ParsePositionError> for UciMoveError {
+    fn from(err: ParsePositionError) -> UciMoveError {
+        UciMoveError::InvalidSquare(err)
+    }
+}
 
 #[wasm_bindgen(inspectable)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Move {
     pub(crate) start: Position,
     pub(crate) end: Position,
+    pub(crate) promotion: Option<Piece>,
 }
 
 #[wasm_bindgen]
 impl Move {
     #[wasm_bindgen(js_name = "toString")]
     pub fn to_string(&self) -> String {
-        format!("{} -> {}", self.start, self.end)
+        match self.promotion {
+            Some(promotion) => format!("{} -> {}={}", self.start, self.end, promotion),
+            None => format!("{} -> {}", self.start, self.end),
+        }
     }
 
     #[wasm_bindgen(js_name = "getStart")]
@@ -26,6 +63,59 @@ impl Move {
         self.end.clone()
     }
 
+    #[wasm_bindgen(js_name = "isPromotion")]
+    pub fn is_promotion(&self) -> bool {
+        self.promotion.is_some()
+    }
+
+    /// Returns the bits of the piece kind this move promotes to, or `0`.
+    #[wasm_bindgen(js_name = "getPromotion")]
+    pub fn get_promotion(&self) -> u8 {
+        self.promotion.map_or(0, |piece| piece.bits())
+    }
+
+    /// Picks the piece a promoting pawn turns into. The color bits are ignored,
+    /// so both `Piece::QUEEN` and `Piece::QUEEN | Piece::WHITE` are accepted.
+    #[wasm_bindgen(js_name = "setPromotion")]
+    pub fn set_promotion(&mut self, piece: u8) -> Result<(), JsValue> {
+        if self.promotion.is_none() {
+            return Err(JsValue::from_str("Move is not a promotion"));
+        }
+
+        match Piece::from_bits(piece).map(|piece| piece.kind()) {
+            Some(kind) if Piece::PROMOTIONS.contains(&kind) => {
+                self.promotion = Some(kind);
+                Ok(())
+            }
+            _ => Err(JsValue::from_str(&format!(
+                "Invalid promotion piece {}",
+                piece
+            ))),
+        }
+    }
+
+    /// Formats the move in Standard Algebraic Notation. Has to be called with
+    /// the board *before* the move is done.
+    #[wasm_bindgen(js_name = "toSan")]
+    pub fn js_to_san(&self, board: &Board) -> String {
+        self.to_san(board)
+    }
+
+    #[wasm_bindgen(js_name = "fromSan")]
+    pub fn js_from_san(board: &Board, san: &str) -> Result<Move, JsValue> {
+        Move::from_san(board, san).map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    #[wasm_bindgen(js_name = "toUci")]
+    pub fn js_to_uci(&self) -> String {
+        self.to_uci()
+    }
+
+    #[wasm_bindgen(js_name = "fromUci")]
+    pub fn js_from_uci(uci: &str) -> Result<Move, JsValue> {
+        Move::from_uci(uci).map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
     #[wasm_bindgen(js_name = "do")]
     pub fn do_move(&self, board: &mut Board) {
         self.execute(board);
@@ -34,12 +124,71 @@ impl Move {
 
 impl Move {
     pub fn new(start: Position, end: Position) -> Move {
-        Move { start, end }
+        Move {
+            start,
+            end,
+            promotion: None,
+        }
+    }
+
+    pub fn with_promotion(start: Position, end: Position, promotion: Piece) -> Move {
+        Move {
+            start,
+            end,
+            promotion: Some(promotion.kind()),
+        }
+    }
+
+    pub fn promotion(&self) -> Option<Piece> {
+        self.promotion
+    }
+
+    /// Formats the move in the long algebraic notation used by UCI, e.g.
+    /// `e2e4`, `e1g1` for castling or `e7e8q` for a promotion.
+    pub fn to_uci(&self) -> String {
+        let mut uci = format!("{}{}", self.start.to_algebraic(), self.end.to_algebraic());
+        if let Some(promotion) = self.promotion {
+            uci.push(promotion.to_char().to_ascii_lowercase());
+        }
+        uci
+    }
+
+    /// Parses a move in UCI long algebraic notation. This only checks the
+    /// syntax, use `Board::is_legal` to make sure the move can be played.
+    pub fn from_uci(uci: &str) -> Result<Move, UciMoveError> {
+        if !uci.is_ascii() || (uci.len() != 4 && uci.len() != 5) {
+            return Err(UciMoveError::InvalidLength(uci.to_string()));
+        }
+
+        let start = uci[0..2].parse()?;
+        let end = uci[2..4].parse()?;
+
+        match uci[4..].chars().next() {
+            Some(c) => match Piece::from_char(c).map(|piece| piece.kind()) {
+                Some(kind) if Piece::PROMOTIONS.contains(&kind) => {
+                    Ok(Move::with_promotion(start, end, kind))
+                }
+                _ => Err(UciMoveError::InvalidPromotion(c)),
+            },
+            None => Ok(Move::new(start, end)),
+        }
+    }
+
+    /// Returns whether this move is a castling move, i.e. the king moves two files.
+    pub fn is_castling(&self, board: &Board) -> bool {
+        board[&self.start].contains(Piece::KING) && (self.end.x - self.start.x).abs() == 2
+    }
+
+    /// Returns whether this move is a pawn capturing onto the en-passant square.
+    pub fn is_en_passant(&self, board: &Board) -> bool {
+        board[&self.start].contains(Piece::PAWN)
+            && self.start.x != self.end.x
+            && board[&self.end] == Piece::NONE
     }
 
+    /// Plays the move on `board`. Use `Board::make_move` instead when the
+    /// move has to be taken back later.
     pub fn execute(&self, board: &mut Board) {
-        board[&self.end] = board[&self.start];
-        board[&self.start] = Piece::NONE;
-        board.turn = board.turn.next();
+        board.make_move(self);
     }
 }
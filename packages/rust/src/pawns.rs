@@ -0,0 +1,195 @@
+//! Pawn structure evaluation.
+//!
+//! Doubled, isolated and backward pawns are penalized and passed pawns get a
+//! bonus growing with their rank. These terms only depend on where the pawns
+//! stand, so they are cached in a `PawnTable` keyed by `Board::pawn_hash`.
+//! Only the distance of the kings to the passed pawns is added afterwards.
+
+use crate::bitboard::{bit, pawn_attacks, squares, Bitboard, FILE_A, FILE_H, RANK_1, RANK_8};
+use crate::board::Board;
+use crate::piece::Piece;
+
+/// Middlegame and endgame penalties of the structural weaknesses.
+const DOUBLED: (i32, i32) = (-10, -20);
+const ISOLATED: (i32, i32) = (-15, -20);
+const BACKWARD: (i32, i32) = (-10, -10);
+
+/// Passed pawn bonuses by rank, counted from the pawn's own side.
+const PASSED_MIDGAME: [i32; 8] = [0, 5, 10, 15, 25, 45, 70, 0];
+const PASSED_ENDGAME: [i32; 8] = [0, 10, 15, 25, 45, 75, 120, 0];
+
+/// Endgame bonus per rank of a passed pawn for every square the enemy king
+/// is away from the square in front of it, and penalty for the own king.
+const ENEMY_KING_DISTANCE: i32 = 5;
+const OWN_KING_DISTANCE: i32 = 2;
+
+/// Number of entries of `PawnTable::default`.
+pub const DEFAULT_ENTRIES: usize = 1 << 14;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PawnEntry {
+    key: u64,
+    /// The score from white's point of view.
+    pub midgame: i32,
+    pub endgame: i32,
+    /// The passed pawns, indexed by color index.
+    pub passed: [Bitboard; 2],
+}
+
+/// Caches pawn structure evaluations by pawn hash. Pawns rarely move compared
+/// to the other pieces, so nearly every lookup is a hit.
+pub struct PawnTable {
+    /// Always a power of two long. An empty entry has key 0, which is also
+    /// the correct entry of a board without pawns.
+    entries: Vec<PawnEntry>,
+}
+
+impl Default for PawnTable {
+    fn default() -> Self {
+        PawnTable::new(DEFAULT_ENTRIES)
+    }
+}
+
+impl PawnTable {
+    /// Creates a table with room for `entries` pawn structures, rounded down
+    /// to a power of two.
+    pub fn new(entries: usize) -> PawnTable {
+        let entries = entries.max(1);
+        let capacity = 1 << (usize::BITS - 1 - entries.leading_zeros());
+
+        PawnTable {
+            entries: vec![PawnEntry::default(); capacity],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(PawnEntry::default());
+    }
+
+    /// Returns the pawn structure evaluation of `board`, evaluating and
+    /// storing it first if it is not cached yet.
+    pub fn probe(&mut self, board: &Board) -> PawnEntry {
+        let key = board.pawn_hash();
+        let index = key as usize & (self.entries.len() - 1);
+
+        if self.entries[index].key != key {
+            self.entries[index] = evaluate_pawns(board);
+        }
+        self.entries[index]
+    }
+}
+
+/// Evaluates the pawn structure of both sides without any caching.
+pub fn evaluate_pawns(board: &Board) -> PawnEntry {
+    let mut entry = PawnEntry {
+        key: board.pawn_hash(),
+        ..PawnEntry::default()
+    };
+
+    for (us, color) in [Piece::WHITE, Piece::BLACK].into_iter().enumerate() {
+        let sign = if us == 0 { 1 } else { -1 };
+        let own = board.pieces_of(Piece::PAWN, color);
+        let enemies = board.pieces_of(Piece::PAWN, color.opposite_color());
+        let enemy_attacks =
+            squares(enemies).fold(0, |attacks, square| attacks | pawn_attacks(1 - us, square));
+
+        // A pawn on the first or last rank, only possible on a board set up
+        // by hand, has no square in front of it and is left out
+        for square in squares(own & !(RANK_1 | RANK_8)) {
+            let (x, y) = (square % 8, square / 8);
+            let file = FILE_A << x;
+            let neighbours = adjacent_files(x);
+            let ahead = ranks_ahead(us, y);
+
+            let mut add = |(midgame, endgame): (i32, i32)| {
+                entry.midgame += sign * midgame;
+                entry.endgame += sign * endgame;
+            };
+
+            // Only the pawns behind another one count as doubled
+            let doubled = own & file & ahead != 0;
+            if doubled {
+                add(DOUBLED);
+            }
+
+            if own & neighbours == 0 {
+                add(ISOLATED);
+            } else {
+                // No neighbour can ever come up to defend it, and it cannot
+                // advance safely either
+                let stop = if us == 0 { square + 8 } else { square - 8 };
+                if own & neighbours & !ahead == 0 && enemy_attacks & bit(stop) != 0 {
+                    add(BACKWARD);
+                }
+            }
+
+            if !doubled && enemies & (file | neighbours) & ahead == 0 {
+                let rank = relative_rank(us, y);
+                add((PASSED_MIDGAME[rank], PASSED_ENDGAME[rank]));
+                entry.passed[us] |= bit(square);
+            }
+        }
+    }
+
+    entry
+}
+
+/// The endgame bonus from white's point of view for kings close to their
+/// own passed pawns and far from the enemy's ones.
+pub fn passed_pawn_kings(board: &Board, passed: [Bitboard; 2]) -> i32 {
+    let mut score = 0;
+
+    for (us, color) in [Piece::WHITE, Piece::BLACK].into_iter().enumerate() {
+        let sign = if us == 0 { 1 } else { -1 };
+        let (Some(own_king), Some(enemy_king)) = (
+            squares(board.pieces_of(Piece::KING, color)).next(),
+            squares(board.pieces_of(Piece::KING, color.opposite_color())).next(),
+        ) else {
+            continue;
+        };
+
+        for square in squares(passed[us] & !(RANK_1 | RANK_8)) {
+            let rank = relative_rank(us, square / 8) as i32;
+            let stop = if us == 0 { square + 8 } else { square - 8 };
+
+            // Only pawns which got going are worth escorting
+            let weight = (rank - 2).max(0);
+            score += sign
+                * weight
+                * (ENEMY_KING_DISTANCE * distance(enemy_king, stop)
+                    - OWN_KING_DISTANCE * distance(own_king, stop));
+        }
+    }
+
+    score
+}
+
+pub(crate) fn adjacent_files(x: usize) -> Bitboard {
+    let file = FILE_A << x;
+    ((file << 1) & !FILE_A) | ((file >> 1) & !FILE_H)
+}
+
+/// All squares in front of rank `y` as seen by the color with index `us`.
+pub(crate) fn ranks_ahead(us: usize, y: usize) -> Bitboard {
+    match us {
+        0 if y < 7 => !0 << (8 * (y + 1)),
+        1 if y > 0 => !0 >> (8 * (8 - y)),
+        _ => 0,
+    }
+}
+
+/// The rank counted from the side of the color with index `us`, from 0 to 7.
+fn relative_rank(us: usize, y: usize) -> usize {
+    if us == 0 {
+        y
+    } else {
+        7 - y
+    }
+}
+
+/// The number of king moves between two squares.
+fn distance(a: usize, b: usize) -> i32 {
+    let dx = (a % 8).abs_diff(b % 8);
+    let dy = (a / 8).abs_diff(b / 8);
+    dx.max(dy) as i32
+}
@@ -0,0 +1,48 @@
+use crate::board::Board;
+use crate::moves::Move;
+
+impl Board {
+    /// Counts the leaf nodes of the legal move tree `depth` plies deep.
+    ///
+    /// Comparing these counts with published numbers is the standard way of
+    /// verifying a move generator.
+    pub fn perft(&self, depth: usize) -> u64 {
+        self.clone().perft_in_place(depth)
+    }
+
+    /// Splits the perft count up by the first move, which makes it easy to
+    /// find the move whose subtree differs from a reference engine.
+    pub fn divide(&self, depth: usize) -> Vec<(Move, u64)> {
+        let mut board = self.clone();
+
+        board
+            .generate_all_moves()
+            .into_iter()
+            .map(|move_| {
+                let undo = board.make_move(&move_);
+                let nodes = board.perft_in_place(depth.saturating_sub(1));
+                board.unmake_move(&move_, undo);
+                (move_, nodes)
+            })
+            .collect()
+    }
+
+    fn perft_in_place(&mut self, depth: usize) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let moves = self.generate_all_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+
+        let mut nodes = 0;
+        for move_ in moves {
+            let undo = self.make_move(&move_);
+            nodes += self.perft_in_place(depth - 1);
+            self.unmake_move(&move_, undo);
+        }
+        nodes
+    }
+}
@@ -0,0 +1,722 @@
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::JsValue;
+
+use crate::board::{Board, Player};
+use crate::fen::{FenError, STARTING_FEN};
+use crate::moves::Move;
+use crate::san::SanError;
+use crate::status::GameStatus;
+
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// The tags every PGN game should carry, in their canonical order.
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// Lines of exported movetext are wrapped before they reach this length.
+const LINE_WIDTH: usize = 80;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnError {
+    /// The input did not contain a single game.
+    NoGame,
+    UnterminatedTag {
+        line: usize,
+    },
+    InvalidTag {
+        line: usize,
+    },
+    UnterminatedComment {
+        line: usize,
+    },
+    /// A `)` without a matching `(`, or a variation before any move.
+    UnexpectedVariation {
+        line: usize,
+    },
+    /// The game ended while a variation was still open.
+    UnterminatedVariation {
+        line: usize,
+    },
+    /// A NAG with nothing to annotate, or an unknown symbol.
+    UnexpectedToken {
+        line: usize,
+        token: String,
+    },
+    InvalidFen(FenError),
+    InvalidMove {
+        line: usize,
+        error: SanError,
+    },
+    /// A move pushed onto the game which is not legal in its position.
+    IllegalMove(Move),
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            PgnError::NoGame => write!(f, "no game found"),
+            PgnError::UnterminatedTag { line } => write!(f, "line {}: unterminated tag", line),
+            PgnError::InvalidTag { line } => write!(f, "line {}: invalid tag pair", line),
+            PgnError::UnterminatedComment { line } => {
+                write!(f, "line {}: unterminated comment", line)
+            }
+            PgnError::UnexpectedVariation { line } => {
+                write!(f, "line {}: unexpected variation", line)
+            }
+            PgnError::UnterminatedVariation { line } => {
+                write!(f, "line {}: unterminated variation", line)
+            }
+            PgnError::UnexpectedToken { line, token } => {
+                write!(f, "line {}: unexpected '{}'", line, token)
+            }
+            PgnError::InvalidFen(err) => write!(f, "invalid FEN tag: {}", err),
+            PgnError::InvalidMove { line, error } => write!(f, "line {}: {}", line, error),
+            PgnError::IllegalMove(move_) => write!(f, "illegal move {}", move_.to_uci()),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+impl From<FenError> for PgnError {
+    fn from(err: FenError) -> PgnError {
+        PgnError::InvalidFen(err)
+    }
+}
+
+/// A single move of a game record together with its annotations.
+#[derive(Debug, Clone)]
+pub struct GameNode {
+    pub move_: Move,
+    pub san: String,
+    /// Numeric annotation glyphs, e.g. `1` for `!` or `4` for `??`.
+    pub nags: Vec<u8>,
+    /// A comment placed in front of the move, only used at the start of a line.
+    pub starting_comment: Option<String>,
+    pub comment: Option<String>,
+    /// Alternative lines, each replacing this move.
+    pub variations: Vec<Vec<GameNode>>,
+}
+
+impl GameNode {
+    fn new(move_: Move, san: String) -> GameNode {
+        GameNode {
+            move_,
+            san,
+            nags: Vec::new(),
+            starting_comment: None,
+            comment: None,
+            variations: Vec::new(),
+        }
+    }
+}
+
+/// A complete game record: the tag pairs and a tree of moves starting from the
+/// initial position.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct Game {
+    tags: Vec<(String, String)>,
+    start: Board,
+    board: Board,
+    moves: Vec<GameNode>,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::from_board(Board::default())
+    }
+}
+
+#[wasm_bindgen]
+impl Game {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Game {
+        Game::default()
+    }
+
+    #[wasm_bindgen(js_name = fromPgn)]
+    pub fn js_from_pgn(pgn: &str) -> Result<Game, JsValue> {
+        Game::from_pgn(pgn).map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    #[wasm_bindgen(js_name = toPgn)]
+    pub fn js_to_pgn(&self) -> String {
+        self.to_pgn()
+    }
+
+    /// Plays `move_` at the end of the main line, throwing if it is illegal.
+    #[wasm_bindgen(js_name = push)]
+    pub fn js_push(&mut self, move_: &Move) -> Result<(), JsValue> {
+        self.push(move_.clone())
+            .map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    #[wasm_bindgen(js_name = getBoard)]
+    pub fn js_board(&self) -> Board {
+        self.board.clone()
+    }
+
+    #[wasm_bindgen(js_name = getTag)]
+    pub fn js_tag(&self, name: &str) -> Option<String> {
+        self.tag(name).map(str::to_string)
+    }
+
+    #[wasm_bindgen(js_name = setTag)]
+    pub fn js_set_tag(&mut self, name: &str, value: &str) {
+        self.set_tag(name, value);
+    }
+}
+
+impl Game {
+    /// Starts a new game from `board`, recording its FEN if it is not the
+    /// standard starting position.
+    pub fn from_board(board: Board) -> Game {
+        let mut game = Game {
+            tags: SEVEN_TAG_ROSTER
+                .iter()
+                .map(|&name| (name.to_string(), "?".to_string()))
+                .collect(),
+            start: board.clone(),
+            board,
+            moves: Vec::new(),
+        };
+        game.set_tag("Result", "*");
+
+        let fen = game.start.to_fen();
+        if fen != STARTING_FEN {
+            game.set_tag("SetUp", "1");
+            game.set_tag("FEN", &fen);
+        }
+
+        game
+    }
+
+    /// Reads the first game of a PGN file.
+    pub fn from_pgn(pgn: &str) -> Result<Game, PgnError> {
+        Parser::new(pgn).parse_game()?.ok_or(PgnError::NoGame)
+    }
+
+    /// Reads every game of a PGN file.
+    pub fn all_from_pgn(pgn: &str) -> Result<Vec<Game>, PgnError> {
+        let mut parser = Parser::new(pgn);
+        let mut games = Vec::new();
+        while let Some(game) = parser.parse_game()? {
+            games.push(game);
+        }
+        Ok(games)
+    }
+
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn result(&self) -> &str {
+        self.tag("Result").unwrap_or("*")
+    }
+
+    /// The position the game started from.
+    pub fn start(&self) -> &Board {
+        &self.start
+    }
+
+    /// The position at the end of the main line.
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// The main line, with the alternatives hanging off its nodes.
+    pub fn moves(&self) -> &[GameNode] {
+        &self.moves
+    }
+
+    pub fn mainline(&self) -> impl Iterator<Item = &Move> {
+        self.moves.iter().map(|node| &node.move_)
+    }
+
+    /// Plays `move_` at the end of the main line and updates the result once
+    /// the game is decided. Illegal moves leave the game unchanged.
+    pub fn push(&mut self, move_: Move) -> Result<(), PgnError> {
+        if !self.board.generate_all_moves().contains(&move_) {
+            return Err(PgnError::IllegalMove(move_));
+        }
+
+        let san = move_.to_san(&self.board);
+        move_.execute(&mut self.board);
+        self.moves.push(GameNode::new(move_, san));
+
+        let result = result_for(self.board.status());
+        self.set_tag("Result", result);
+        Ok(())
+    }
+
+    /// Serializes the game in export format.
+    pub fn to_pgn(&self) -> String {
+        let mut out = String::new();
+
+        for (name, value) in &self.tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            out.push_str(&format!("[{} \"{}\"]\n", name, value));
+        }
+        out.push('\n');
+
+        let mut tokens = Vec::new();
+        write_line(&mut tokens, &self.moves, first_ply(&self.start), true);
+        tokens.push(self.result().to_string());
+
+        let mut line = String::new();
+        for token in tokens {
+            let glue = !line.is_empty() && !line.ends_with('(') && !token.starts_with(')');
+            if glue && line.len() + token.len() + 1 > LINE_WIDTH {
+                out.push_str(&line);
+                out.push('\n');
+                line.clear();
+            } else if glue {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        out.push_str(&line);
+        out.push('\n');
+
+        out
+    }
+}
+
+/// The PGN result token belonging to a game status.
+pub fn result_for(status: GameStatus) -> &'static str {
+    match status {
+        GameStatus::Ongoing => "*",
+        GameStatus::WhiteWins => "1-0",
+        GameStatus::BlackWins => "0-1",
+        _ => "1/2-1/2",
+    }
+}
+
+/// Counts halfmoves from the start of the game, so that white's first move is
+/// ply 0 and the move number is `ply / 2 + 1`.
+fn first_ply(board: &Board) -> u32 {
+    let black = board.turn == Player::Black;
+    (board.fullmove_number() - 1) * 2 + black as u32
+}
+
+fn write_line(tokens: &mut Vec<String>, line: &[GameNode], mut ply: u32, mut numbered: bool) {
+    for node in line {
+        if let Some(comment) = &node.starting_comment {
+            tokens.push(format!("{{{}}}", comment));
+            numbered = true;
+        }
+
+        if ply.is_multiple_of(2) {
+            tokens.push(format!("{}.", ply / 2 + 1));
+        } else if numbered {
+            tokens.push(format!("{}...", ply / 2 + 1));
+        }
+        tokens.push(node.san.clone());
+        numbered = false;
+
+        for nag in &node.nags {
+            tokens.push(format!("${}", nag));
+        }
+
+        if let Some(comment) = &node.comment {
+            tokens.push(format!("{{{}}}", comment));
+            numbered = true;
+        }
+
+        for variation in &node.variations {
+            tokens.push("(".to_string());
+            write_line(tokens, variation, ply, true);
+            tokens.push(")".to_string());
+            numbered = true;
+        }
+
+        ply += 1;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Nag(u8),
+    OpenVariation,
+    CloseVariation,
+    San(String),
+    Result(String),
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    line_start: bool,
+    peeked: Option<Token>,
+    /// The second half of a symbol that is split into two tokens, e.g. the
+    /// NAG of `e4!`.
+    pending: Option<Token>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Parser<'a> {
+        Parser {
+            chars: input.chars().peekable(),
+            line: 1,
+            line_start: true,
+            peeked: None,
+            pending: None,
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        self.line_start = c == Some('\n');
+        c
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.bump() {
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+
+    fn peek_token(&mut self) -> Result<Option<&Token>, PgnError> {
+        if self.peeked.is_none() {
+            self.peeked = self.read_token()?;
+        }
+        Ok(self.peeked.as_ref())
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, PgnError> {
+        match self.peeked.take() {
+            Some(token) => Ok(Some(token)),
+            None => self.read_token(),
+        }
+    }
+
+    fn read_token(&mut self) -> Result<Option<Token>, PgnError> {
+        if let Some(token) = self.pending.take() {
+            return Ok(Some(token));
+        }
+
+        loop {
+            let c = match self.chars.peek() {
+                Some(&c) => c,
+                None => return Ok(None),
+            };
+
+            match c {
+                c if c.is_whitespace() => {
+                    self.bump();
+                }
+                // Escaped lines are reserved for other programs and ignored
+                '%' if self.line_start => self.skip_line(),
+                ';' => {
+                    self.bump();
+                    let mut comment = String::new();
+                    while let Some(&c) = self.chars.peek() {
+                        if c == '\n' {
+                            break;
+                        }
+                        comment.push(c);
+                        self.bump();
+                    }
+                    return Ok(Some(Token::Comment(comment.trim().to_string())));
+                }
+                '{' => {
+                    let line = self.line;
+                    self.bump();
+                    let mut comment = String::new();
+                    loop {
+                        match self.bump() {
+                            Some('}') => break,
+                            Some(c) => comment.push(c),
+                            None => return Err(PgnError::UnterminatedComment { line }),
+                        }
+                    }
+                    return Ok(Some(Token::Comment(comment.trim().to_string())));
+                }
+                '[' => return self.read_tag().map(Some),
+                '(' => {
+                    self.bump();
+                    return Ok(Some(Token::OpenVariation));
+                }
+                ')' => {
+                    self.bump();
+                    return Ok(Some(Token::CloseVariation));
+                }
+                '$' => {
+                    self.bump();
+                    let digits = self.read_symbol();
+                    return digits.parse().map(Token::Nag).map(Some).map_err(|_| {
+                        PgnError::UnexpectedToken {
+                            line: self.line,
+                            token: format!("${}", digits),
+                        }
+                    });
+                }
+                _ => {
+                    if let Some(token) = self.read_move_token()? {
+                        return Ok(Some(token));
+                    }
+                }
+            }
+        }
+    }
+
+    fn read_symbol(&mut self) -> String {
+        let mut symbol = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_whitespace() || "{}()[];$".contains(c) {
+                break;
+            }
+            symbol.push(c);
+            self.bump();
+        }
+        symbol
+    }
+
+    /// Reads a move, result or suffix annotation. Move numbers carry no
+    /// information and are skipped, in which case `None` is returned.
+    fn read_move_token(&mut self) -> Result<Option<Token>, PgnError> {
+        let symbol = self.read_symbol();
+        if symbol.is_empty() {
+            let c = self.bump().unwrap_or_default();
+            return Err(PgnError::UnexpectedToken {
+                line: self.line,
+                token: c.to_string(),
+            });
+        }
+
+        if matches!(symbol.as_str(), "1-0" | "0-1" | "1/2-1/2" | "*") {
+            return Ok(Some(Token::Result(symbol)));
+        }
+
+        if let Some(nag) = suffix_nag(&symbol) {
+            return Ok(Some(Token::Nag(nag)));
+        }
+
+        // Move numbers like `12.` or `12...`, possibly glued to the move
+        let rest = symbol.trim_start_matches(|c: char| c.is_ascii_digit());
+        let san = if rest.is_empty() || rest.starts_with('.') {
+            rest.trim_start_matches('.')
+        } else {
+            symbol.as_str()
+        };
+
+        if san.is_empty() {
+            return Ok(None);
+        }
+
+        // Split off suffix annotations like `!?` so they can become NAGs
+        let annotation = san.trim_start_matches(|c| c != '!' && c != '?');
+        let san = &san[..san.len() - annotation.len()];
+        if !annotation.is_empty() {
+            match suffix_nag(annotation) {
+                Some(nag) => self.pending = Some(Token::Nag(nag)),
+                None => {
+                    return Err(PgnError::UnexpectedToken {
+                        line: self.line,
+                        token: symbol.clone(),
+                    })
+                }
+            }
+        }
+
+        Ok(Some(Token::San(san.to_string())))
+    }
+
+    fn read_tag(&mut self) -> Result<Token, PgnError> {
+        let line = self.line;
+        self.bump();
+
+        let mut content = String::new();
+        let mut in_string = false;
+        loop {
+            match self.bump() {
+                Some(']') if !in_string => break,
+                Some('\\') if in_string => match self.bump() {
+                    Some(c) => content.push(c),
+                    None => return Err(PgnError::UnterminatedTag { line }),
+                },
+                Some('"') => {
+                    in_string = !in_string;
+                    content.push('"');
+                }
+                Some(c) => content.push(c),
+                None => return Err(PgnError::UnterminatedTag { line }),
+            }
+        }
+
+        let content = content.trim();
+        let (name, value) = content
+            .split_once(char::is_whitespace)
+            .ok_or(PgnError::InvalidTag { line })?;
+        let value = value.trim();
+
+        if name.is_empty()
+            || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            || value.len() < 2
+            || !value.starts_with('"')
+            || !value.ends_with('"')
+        {
+            return Err(PgnError::InvalidTag { line });
+        }
+
+        Ok(Token::Tag(
+            name.to_string(),
+            value[1..value.len() - 1].to_string(),
+        ))
+    }
+
+    fn parse_game(&mut self) -> Result<Option<Game>, PgnError> {
+        let mut tags = Vec::new();
+        while let Some(Token::Tag(..)) = self.peek_token()? {
+            if let Some(Token::Tag(name, value)) = self.next_token()? {
+                tags.push((name, value));
+            }
+        }
+
+        if tags.is_empty() && self.peek_token()?.is_none() {
+            return Ok(None);
+        }
+
+        let start = match tags.iter().find(|(name, _)| name == "FEN") {
+            Some((_, fen)) => Board::from_fen(fen)?,
+            None => Board::default(),
+        };
+
+        let mut game = Game::from_board(start);
+        for (name, value) in tags {
+            game.set_tag(&name, &value);
+        }
+
+        let mut board = game.start.clone();
+        let (moves, result) = self.parse_line(&mut board, false)?;
+
+        game.board = board;
+        game.moves = moves;
+        if let Some(result) = result {
+            game.set_tag("Result", &result);
+        }
+
+        Ok(Some(game))
+    }
+
+    /// Parses moves until the end of the current variation or the game,
+    /// leaving `board` at the position after the last move of the line.
+    fn parse_line(
+        &mut self,
+        board: &mut Board,
+        in_variation: bool,
+    ) -> Result<(Vec<GameNode>, Option<String>), PgnError> {
+        let mut line: Vec<GameNode> = Vec::new();
+        let mut before_last = board.clone();
+        let mut pending_comment: Option<String> = None;
+
+        loop {
+            // A tag means the next game started without a result token
+            if let Some(Token::Tag(..)) = self.peek_token()? {
+                if in_variation {
+                    return Err(PgnError::UnterminatedVariation { line: self.line });
+                }
+                return Ok((line, None));
+            }
+
+            let token = match self.next_token()? {
+                Some(token) => token,
+                None if in_variation => {
+                    return Err(PgnError::UnterminatedVariation { line: self.line })
+                }
+                None => return Ok((line, None)),
+            };
+
+            match token {
+                Token::San(san) => {
+                    let move_ =
+                        Move::from_san(board, &san).map_err(|error| PgnError::InvalidMove {
+                            line: self.line,
+                            error,
+                        })?;
+                    let san = move_.to_san(board);
+
+                    before_last = board.clone();
+                    move_.execute(board);
+
+                    let mut node = GameNode::new(move_, san);
+                    node.starting_comment = pending_comment.take();
+                    line.push(node);
+                }
+                Token::Nag(nag) => match line.last_mut() {
+                    Some(node) => node.nags.push(nag),
+                    None => {
+                        return Err(PgnError::UnexpectedToken {
+                            line: self.line,
+                            token: format!("${}", nag),
+                        })
+                    }
+                },
+                Token::Comment(comment) => {
+                    let target = match line.last_mut() {
+                        Some(node) => &mut node.comment,
+                        None => &mut pending_comment,
+                    };
+                    *target = Some(match target.take() {
+                        Some(old) => format!("{} {}", old, comment),
+                        None => comment,
+                    });
+                }
+                Token::OpenVariation => {
+                    let mut variation_board = before_last.clone();
+                    let (variation, _) = self.parse_line(&mut variation_board, true)?;
+                    match line.last_mut() {
+                        Some(node) if !variation.is_empty() => node.variations.push(variation),
+                        Some(_) => {}
+                        None => return Err(PgnError::UnexpectedVariation { line: self.line }),
+                    }
+                }
+                Token::CloseVariation if in_variation => return Ok((line, None)),
+                Token::CloseVariation => {
+                    return Err(PgnError::UnexpectedVariation { line: self.line })
+                }
+                Token::Result(_) if in_variation => {
+                    return Err(PgnError::UnterminatedVariation { line: self.line })
+                }
+                Token::Result(result) => return Ok((line, Some(result))),
+                Token::Tag(..) => unreachable!("tags are handled before reading the token"),
+            }
+        }
+    }
+}
+
+/// Maps the traditional suffix annotations to their NAG.
+fn suffix_nag(annotation: &str) -> Option<u8> {
+    match annotation {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
@@ -1,5 +1,4 @@
 use bitflags::bitflags;
-use wasm_bindgen::prelude::*;
 
 use std::fmt;
 
@@ -24,6 +23,59 @@ bitflags! {
 
 impl fmt::Display for Piece {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+impl Piece {
+    /// The piece kinds a pawn may promote to, best first.
+    pub const PROMOTIONS: [Piece; 4] = [Piece::QUEEN, Piece::ROOK, Piece::BISHOP, Piece::KNIGHT];
+
+    /// All piece kinds, ordered by `kind_index`.
+    pub const KINDS: [Piece; 6] = [
+        Piece::PAWN,
+        Piece::KNIGHT,
+        Piece::BISHOP,
+        Piece::ROOK,
+        Piece::QUEEN,
+        Piece::KING,
+    ];
+
+    pub fn opposite(&self) -> Piece {
+        let mut out = *self;
+        out.toggle(Piece::WHITE);
+        out.toggle(Piece::BLACK);
+        out
+    }
+    pub fn opposite_color(&self) -> Piece {
+        let mut out = *self;
+        out.toggle(Piece::WHITE);
+        out.toggle(Piece::BLACK);
+        out & Piece::COLOR
+    }
+
+    pub fn color(&self) -> Piece {
+        *self & Piece::COLOR
+    }
+
+    pub fn kind(&self) -> Piece {
+        *self & Piece::PIECE
+    }
+
+    /// Index of the piece kind into per kind tables, from 0 for pawns to 5 for
+    /// kings. Only meaningful for actual pieces.
+    pub fn kind_index(&self) -> usize {
+        self.kind().bits().trailing_zeros() as usize - 2
+    }
+
+    /// Index of the color into per color tables, 0 for white and 1 for black.
+    pub fn color_index(&self) -> usize {
+        self.color().bits().trailing_zeros() as usize
+    }
+
+    /// The piece letter as used by FEN, uppercase for white and `.` for an
+    /// empty square.
+    pub fn to_char(&self) -> char {
         let mut out = '.';
         if self.contains(Piece::PAWN) {
             out = 'P';
@@ -43,26 +95,26 @@ impl fmt::Display for Piece {
             out = out.to_ascii_lowercase();
         }
 
-        write!(f, "{}", out)
-    }
-}
-
-impl Piece {
-    pub fn opposite(&self) -> Piece {
-        let mut out = *self;
-        out.toggle(Piece::WHITE);
-        out.toggle(Piece::BLACK);
         out
     }
-    pub fn opposite_color(&self) -> Piece {
-        let mut out = *self;
-        out.toggle(Piece::WHITE);
-        out.toggle(Piece::BLACK);
-        out & Piece::COLOR
-    }
 
-    pub fn color(&self) -> Piece {
-        *self & Piece::COLOR
+    /// Parses a piece letter as used by FEN, uppercase being white.
+    pub fn from_char(c: char) -> Option<Piece> {
+        let kind = match c.to_ascii_uppercase() {
+            'P' => Piece::PAWN,
+            'N' => Piece::KNIGHT,
+            'B' => Piece::BISHOP,
+            'R' => Piece::ROOK,
+            'Q' => Piece::QUEEN,
+            'K' => Piece::KING,
+            _ => return None,
+        };
+
+        if c.is_ascii_uppercase() {
+            Some(kind | Piece::WHITE)
+        } else {
+            Some(kind | Piece::BLACK)
+        }
     }
 
     pub fn score(&self) -> i32 {
@@ -1,9 +1,10 @@
 use wasm_bindgen::prelude::*;
 
 use std::fmt;
+use std::str::FromStr;
 
 #[wasm_bindgen]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Position {
     pub x: i8,
     pub y: i8,
@@ -11,8 +12,7 @@ pub struct Position {
 
 impl fmt::Display for Position {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        let x = (self.x as u8 + 'A' as u8) as char;
-        write!(f, "{}{}", x, self.y + 1)
+        write!(f, "{}", self.to_algebraic())
     }
 }
 
@@ -24,6 +24,46 @@ impl From<i32> for Position {
     }
 }
 
+impl From<usize> for Position {
+    fn from(i: usize) -> Position {
+        Position::from(i as i32)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsePositionError(pub String);
+
+impl fmt::Display for ParsePositionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "invalid square '{}'", self.0)
+    }
+}
+
+impl std::error::Error for ParsePositionError {}
+
+impl FromStr for Position {
+    type Err = ParsePositionError;
+
+    /// Parses a square in coordinate notation like `e4`. Uppercase files are
+    /// accepted as well.
+    fn from_str(s: &str) -> Result<Position, ParsePositionError> {
+        let mut chars = s.chars();
+        let (file, rank) = match (chars.next(), chars.next(), chars.next()) {
+            (Some(file), Some(rank), None) => (file.to_ascii_lowercase(), rank),
+            _ => return Err(ParsePositionError(s.to_string())),
+        };
+
+        if !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+            return Err(ParsePositionError(s.to_string()));
+        }
+
+        Ok(Position::new(
+            file as i8 - 'a' as i8,
+            rank as i8 - '1' as i8,
+        ))
+    }
+}
+
 #[wasm_bindgen]
 impl Position {
     #[wasm_bindgen(constructor)]
@@ -36,6 +76,18 @@ impl Position {
         self.x < 8 && self.y < 8 && self.x >= 0 && self.y >= 0
     }
 
+    #[wasm_bindgen(js_name = fromAlgebraic)]
+    pub fn js_from_algebraic(s: &str) -> Result<Position, JsValue> {
+        s.parse()
+            .map_err(|err: ParsePositionError| JsValue::from_str(&err.to_string()))
+    }
+
+    /// The square in coordinate notation, e.g. `e4`.
+    #[wasm_bindgen(js_name = toAlgebraic)]
+    pub fn to_algebraic(&self) -> String {
+        format!("{}{}", self.file_char(), self.y + 1)
+    }
+
     // #[wasm_bindgen(getter = x)]
     // pub fn get_x(&self) -> i8 {
     //     self.x
@@ -56,3 +108,15 @@ impl Position {
     //     self.y = y;
     // }
 }
+
+impl Position {
+    /// The square index used by `Board` and its bitboards, `y * 8 + x`.
+    pub fn index(&self) -> usize {
+        self.y as usize * 8 + self.x as usize
+    }
+
+    /// The lowercase letter of the file, `a` to `h`.
+    pub fn file_char(&self) -> char {
+        (b'a' + self.x as u8) as char
+    }
+}
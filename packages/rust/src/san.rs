@@ -0,0 +1,167 @@
+use crate::board::Board;
+use crate::moves::Move;
+use crate::piece::Piece;
+use crate::pos::Position;
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanError {
+    /// The string is not valid Standard Algebraic Notation.
+    Invalid(String),
+    /// The notation is well formed, but no legal move matches it.
+    Illegal(String),
+    /// More than one legal move matches the notation.
+    Ambiguous(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            SanError::Invalid(san) => write!(f, "invalid move notation '{}'", san),
+            SanError::Illegal(san) => write!(f, "illegal move '{}'", san),
+            SanError::Ambiguous(san) => write!(f, "ambiguous move '{}'", san),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+impl Move {
+    /// Formats the move in Standard Algebraic Notation, e.g. `Nbd7`, `exd5`,
+    /// `e8=Q+` or `O-O-O#`. `board` is the position before the move is played.
+    pub fn to_san(&self, board: &Board) -> String {
+        let mut san = String::new();
+
+        if self.is_castling(board) {
+            san.push_str(if self.end.x == 6 { "O-O" } else { "O-O-O" });
+        } else {
+            let piece = board[&self.start];
+            let capture = board[&self.end] != Piece::NONE || self.is_en_passant(board);
+
+            if piece.contains(Piece::PAWN) {
+                if capture {
+                    san.push(self.start.file_char());
+                }
+            } else {
+                san.push_str(&piece.kind().to_string());
+
+                // Other pieces of the same kind that could also reach the target
+                let others: Vec<Move> = board
+                    .generate_all_moves()
+                    .into_iter()
+                    .filter(|m| m.end == self.end && m.start != self.start)
+                    .filter(|m| board[&m.start] == piece)
+                    .collect();
+
+                if !others.is_empty() {
+                    if others.iter().all(|m| m.start.x != self.start.x) {
+                        san.push(self.start.file_char());
+                    } else if others.iter().all(|m| m.start.y != self.start.y) {
+                        san.push_str(&(self.start.y + 1).to_string());
+                    } else {
+                        san.push_str(&self.start.to_algebraic());
+                    }
+                }
+            }
+
+            if capture {
+                san.push('x');
+            }
+            san.push_str(&self.end.to_algebraic());
+
+            if let Some(promotion) = self.promotion {
+                san.push('=');
+                san.push_str(&promotion.to_string());
+            }
+        }
+
+        let mut after = board.clone();
+        self.execute(&mut after);
+        if after.in_check() {
+            san.push(if after.generate_all_moves().is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+
+        san
+    }
+
+    /// Parses a move in Standard Algebraic Notation, resolving it against the
+    /// legal moves of `board`.
+    ///
+    /// Check and annotation suffixes are ignored, and a few common deviations
+    /// like `0-0`, `exd6e.p.` or `e8Q` are accepted as well.
+    pub fn from_san(board: &Board, san: &str) -> Result<Move, SanError> {
+        let trimmed = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        let trimmed = trimmed.strip_suffix("e.p.").unwrap_or(trimmed);
+        let invalid = || SanError::Invalid(san.to_string());
+
+        let moves = board.generate_all_moves();
+
+        let castling_file = match trimmed {
+            "O-O" | "0-0" => Some(6),
+            "O-O-O" | "0-0-0" => Some(2),
+            _ => None,
+        };
+        if let Some(file) = castling_file {
+            return moves
+                .into_iter()
+                .find(|m| m.is_castling(board) && m.end.x == file)
+                .ok_or_else(|| SanError::Illegal(san.to_string()));
+        }
+
+        let (kind, rest) = match trimmed.chars().next() {
+            Some(c @ ('N' | 'B' | 'R' | 'Q' | 'K')) => (
+                Piece::from_char(c).ok_or_else(invalid)?.kind(),
+                &trimmed[1..],
+            ),
+            Some(_) => (Piece::PAWN, trimmed),
+            None => return Err(invalid()),
+        };
+
+        let (rest, promotion) = match rest.char_indices().last() {
+            Some((i, c @ ('N' | 'B' | 'R' | 'Q'))) if kind == Piece::PAWN => {
+                let promotion = Piece::from_char(c).ok_or_else(invalid)?.kind();
+                (
+                    rest[..i].strip_suffix('=').unwrap_or(&rest[..i]),
+                    Some(promotion),
+                )
+            }
+            _ => (rest, None),
+        };
+
+        if rest.len() < 2 || !rest.is_char_boundary(rest.len() - 2) {
+            return Err(invalid());
+        }
+        let (disambiguation, target) = rest.split_at(rest.len() - 2);
+        let end: Position = target.parse().map_err(|_| invalid())?;
+
+        let mut file = None;
+        let mut rank = None;
+        for c in disambiguation.chars() {
+            match c {
+                'a'..='h' => file = Some(c as i8 - 'a' as i8),
+                '1'..='8' => rank = Some(c as i8 - '1' as i8),
+                'x' | ':' | '-' => {}
+                _ => return Err(invalid()),
+            }
+        }
+
+        let mut candidates = moves.into_iter().filter(|m| {
+            board[&m.start].kind() == kind
+                && m.end == end
+                && m.promotion == promotion
+                && file.is_none_or(|x| m.start.x == x)
+                && rank.is_none_or(|y| m.start.y == y)
+        });
+
+        match (candidates.next(), candidates.next()) {
+            (Some(move_), None) => Ok(move_),
+            (Some(_), Some(_)) => Err(SanError::Ambiguous(san.to_string())),
+            (None, _) => Err(SanError::Illegal(san.to_string())),
+        }
+    }
+}
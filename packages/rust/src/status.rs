@@ -0,0 +1,42 @@
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::board::Player;
+
+/// The state of a game as seen from the current position.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameStatus {
+    Ongoing,
+    /// Black is checkmated.
+    WhiteWins,
+    /// White is checkmated.
+    BlackWins,
+    Stalemate,
+    FiftyMoveRule,
+    ThreefoldRepetition,
+    InsufficientMaterial,
+}
+
+impl GameStatus {
+    pub fn is_over(&self) -> bool {
+        *self != GameStatus::Ongoing
+    }
+
+    pub fn is_draw(&self) -> bool {
+        matches!(
+            self,
+            GameStatus::Stalemate
+                | GameStatus::FiftyMoveRule
+                | GameStatus::ThreefoldRepetition
+                | GameStatus::InsufficientMaterial
+        )
+    }
+
+    pub fn winner(&self) -> Option<Player> {
+        match self {
+            GameStatus::WhiteWins => Some(Player::White),
+            GameStatus::BlackWins => Some(Player::Black),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,285 @@
+//! A fixed-size hash table remembering search results by Zobrist hash, so
+//! positions reached through different move orders are only searched once.
+
+use std::mem;
+
+use crate::ai::is_mate_score;
+use crate::moves::Move;
+
+/// Memory used by `TranspositionTable::default`, in megabytes.
+pub const DEFAULT_SIZE_MB: usize = 16;
+
+/// How the stored score relates to the real score of the position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// The score is exact.
+    Exact,
+    /// The search failed high, the real score is at least this high.
+    Lower,
+    /// The search failed low, the real score is at most this high.
+    Upper,
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub key: u64,
+    /// The remaining depth the position was searched with.
+    pub depth: u8,
+    pub bound: Bound,
+    pub score: i32,
+    /// The best move found, or the move which caused the cutoff.
+    pub best_move: Option<Move>,
+}
+
+/// Counters for judging how well the table works.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub probes: u64,
+    pub hits: u64,
+    pub stores: u64,
+}
+
+impl Stats {
+    /// The share of probes which found an entry, between 0 and 1.
+    pub fn hit_rate(&self) -> f64 {
+        if self.probes == 0 {
+            0.0
+        } else {
+            self.hits as f64 / self.probes as f64
+        }
+    }
+}
+
+pub struct TranspositionTable {
+    /// Always a power of two long, so the index is just the low bits of the key.
+    entries: Vec<Option<Entry>>,
+    used: usize,
+    stats: Stats,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(DEFAULT_SIZE_MB)
+    }
+}
+
+impl TranspositionTable {
+    /// Creates a table using at most `megabytes` of memory.
+    pub fn new(megabytes: usize) -> TranspositionTable {
+        let bytes = megabytes.max(1) * 1024 * 1024;
+        let capacity = bytes / mem::size_of::<Option<Entry>>();
+
+        // Round down to a power of two
+        let capacity = 1 << (usize::BITS - 1 - capacity.leading_zeros());
+
+        TranspositionTable {
+            entries: vec![None; capacity],
+            used: 0,
+            stats: Stats::default(),
+        }
+    }
+
+    /// The number of entries the table can hold.
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Removes all entries and resets the statistics.
+    pub fn clear(&mut self) {
+        self.entries.iter_mut().for_each(|entry| *entry = None);
+        self.used = 0;
+        self.stats = Stats::default();
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Starts counting anew, e.g. for a new search, keeping the entries.
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::default();
+    }
+
+    /// How full the table is in permille, as reported by UCI's `hashfull`.
+    pub fn hashfull(&self) -> usize {
+        self.used * 1000 / self.capacity()
+    }
+
+    /// Looks up the entry for the position with the given hash.
+    pub fn probe(&mut self, key: u64) -> Option<&Entry> {
+        self.stats.probes += 1;
+
+        let index = self.index(key);
+        let entry = self.entries[index]
+            .as_ref()
+            .filter(|entry| entry.key == key);
+        if entry.is_some() {
+            self.stats.hits += 1;
+        }
+        entry
+    }
+
+    /// Stores a search result. An entry of the same position is only replaced
+    /// by a search which was at least as deep, other positions are always
+    /// replaced.
+    pub fn store(
+        &mut self,
+        key: u64,
+        depth: usize,
+        bound: Bound,
+        score: i32,
+        best_move: Option<Move>,
+    ) {
+        let depth = depth.min(u8::MAX as usize) as u8;
+        let index = self.index(key);
+
+        match &self.entries[index] {
+            Some(old) if old.key == key && old.depth > depth => return,
+            Some(_) => {}
+            None => self.used += 1,
+        }
+
+        self.stats.stores += 1;
+        self.entries[index] = Some(Entry {
+            key,
+            depth,
+            bound,
+            score,
+            best_move,
+        });
+    }
+
+    fn index(&self, key: u64) -> usize {
+        key as usize & (self.entries.len() - 1)
+    }
+}
+
+/// Mate scores are relative to the root, but the table is shared between
+/// nodes at different plies, so they are stored relative to the node.
+pub(crate) fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if !is_mate_score(score) {
+        score
+    } else if score > 0 {
+        score + ply as i32
+    } else {
+        score - ply as i32
+    }
+}
+
+/// Turns a score from `score_to_tt` back into one relative to the root.
+pub(crate) fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if !is_mate_score(score) {
+        score
+    } else if score > 0 {
+        score - ply as i32
+    } else {
+        score + ply as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{mate_in, MATE_SCORE};
+
+    fn move_(uci: &str) -> Move {
+        Move::from_uci(uci).unwrap()
+    }
+
+    #[test]
+    fn probe_finds_stored_entries() {
+        let mut tt = TranspositionTable::new(1);
+        assert!(tt.probe(42).is_none());
+
+        tt.store(42, 3, Bound::Lower, 120, Some(move_("e2e4")));
+        let entry = tt.probe(42).unwrap();
+        assert_eq!(entry.depth, 3);
+        assert_eq!(entry.bound, Bound::Lower);
+        assert_eq!(entry.score, 120);
+        assert_eq!(entry.best_move, Some(move_("e2e4")));
+
+        // A different position mapping to the same slot is not a hit
+        let other = 42 + tt.capacity() as u64;
+        assert!(tt.probe(other).is_none());
+    }
+
+    #[test]
+    fn deeper_results_are_kept() {
+        let mut tt = TranspositionTable::new(1);
+        tt.store(7, 5, Bound::Exact, 10, None);
+        tt.store(7, 4, Bound::Exact, 20, None);
+        assert_eq!(tt.probe(7).unwrap().score, 10);
+
+        tt.store(7, 5, Bound::Upper, 30, None);
+        assert_eq!(tt.probe(7).unwrap().score, 30);
+        tt.store(7, 6, Bound::Exact, 40, None);
+        assert_eq!(tt.probe(7).unwrap().score, 40);
+
+        // Another position always takes the slot over
+        let other = 7 + tt.capacity() as u64;
+        tt.store(other, 1, Bound::Exact, 50, None);
+        assert!(tt.probe(7).is_none());
+        assert_eq!(tt.probe(other).unwrap().score, 50);
+    }
+
+    #[test]
+    fn mate_scores_are_stored_relative_to_the_node() {
+        // Mate in two plies from a node five plies below the root
+        let score = MATE_SCORE - 7;
+        let stored = score_to_tt(score, 5);
+        assert_eq!(stored, MATE_SCORE - 2);
+        assert_eq!(score_from_tt(stored, 5), score);
+
+        // Reached again one ply from the root, the mate is now three plies away
+        assert_eq!(mate_in(score_from_tt(stored, 1)), Some(2));
+        assert_eq!(score_from_tt(-stored, 1), -(MATE_SCORE - 3));
+
+        // Other scores are independent of the ply
+        assert_eq!(score_to_tt(-250, 5), -250);
+        assert_eq!(score_from_tt(250, 5), 250);
+    }
+
+    #[test]
+    fn clear_removes_entries_and_stats() {
+        let mut tt = TranspositionTable::new(1);
+        tt.store(1, 1, Bound::Exact, 0, None);
+        tt.probe(1);
+        tt.clear();
+
+        assert!(tt.probe(1).is_none());
+        assert_eq!(tt.hashfull(), 0);
+        assert_eq!(
+            tt.stats(),
+            Stats {
+                probes: 1,
+                hits: 0,
+                stores: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn hit_rate_and_hashfull() {
+        let mut tt = TranspositionTable::new(1);
+        assert_eq!(tt.stats().hit_rate(), 0.0);
+
+        let entries = tt.capacity() / 4;
+        for key in 0..entries as u64 {
+            tt.store(key, 1, Bound::Exact, 0, None);
+        }
+        // Replacing an entry does not use up another slot
+        tt.store(0, 2, Bound::Exact, 0, None);
+        assert_eq!(tt.hashfull(), 250);
+
+        tt.probe(0);
+        tt.probe(1);
+        tt.probe(entries as u64);
+        tt.probe(entries as u64 + 1);
+        assert_eq!(tt.stats().hit_rate(), 0.5);
+        assert_eq!(tt.stats().stores, entries as u64 + 1);
+
+        tt.reset_stats();
+        assert_eq!(tt.stats(), Stats::default());
+        assert_eq!(tt.hashfull(), 250);
+    }
+}
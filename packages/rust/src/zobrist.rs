@@ -0,0 +1,58 @@
+//! Zobrist keys for hashing positions.
+//!
+//! Every piece on every square, every set of castling rights, every
+//! en-passant file and the side to move get a random 64-bit key. The hash of
+//! a position is the xor of the keys of everything in it, so a move only has
+//! to xor out what it removes and xor in what it adds.
+
+use std::sync::OnceLock;
+
+use crate::bitboard::XorShift;
+use crate::board::CastlingRights;
+use crate::piece::Piece;
+
+struct Keys {
+    /// Indexed by color index, kind index and square.
+    pieces: [[[u64; 64]; 6]; 2],
+    castling: [u64; 16],
+    en_passant: [u64; 8],
+    black_to_move: u64,
+}
+
+fn keys() -> &'static Keys {
+    static KEYS: OnceLock<Keys> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        let mut rng = XorShift(0x2f8e_4d3c_1b0a_9f87);
+        let mut keys = Keys {
+            pieces: [[[0; 64]; 6]; 2],
+            castling: [0; 16],
+            en_passant: [0; 8],
+            black_to_move: rng.next(),
+        };
+
+        for key in keys.pieces.iter_mut().flatten().flatten() {
+            *key = rng.next();
+        }
+        for key in keys.castling.iter_mut().chain(keys.en_passant.iter_mut()) {
+            *key = rng.next();
+        }
+
+        keys
+    })
+}
+
+pub fn piece(piece: Piece, square: usize) -> u64 {
+    keys().pieces[piece.color_index()][piece.kind_index()][square]
+}
+
+pub fn castling(rights: CastlingRights) -> u64 {
+    keys().castling[rights.bits() as usize]
+}
+
+pub fn en_passant(file: i8) -> u64 {
+    keys().en_passant[file as usize]
+}
+
+pub fn black_to_move() -> u64 {
+    keys().black_to_move
+}
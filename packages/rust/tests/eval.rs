@@ -0,0 +1,88 @@
+use vite_wasm_functions::bitboard::bit;
+use vite_wasm_functions::board::Board;
+use vite_wasm_functions::eval::evaluate;
+use vite_wasm_functions::king_safety::evaluate_king_safety;
+use vite_wasm_functions::pawns::evaluate_pawns;
+use vite_wasm_functions::piece::Piece;
+
+#[test]
+fn start_position_is_balanced() {
+    assert_eq!(evaluate(&Board::default()), 0);
+}
+
+#[test]
+fn mirrored_positions_evaluate_equally() {
+    // Each pair is the same position with the colors swapped
+    let pairs = [
+        (
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/pppbbppp/2n2q1P/1P2p3/3pn3/BN2PNP1/P1PPQPB1/R3K2R b KQkq - 0 1",
+        ),
+        (
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "8/4p1p1/8/1r3P1K/kp5R/3P4/2P5/8 b - - 0 1",
+        ),
+    ];
+
+    for (fen, mirrored) in pairs {
+        let board = Board::from_fen(fen).unwrap();
+        let mirrored = Board::from_fen(mirrored).unwrap();
+        assert_eq!(evaluate(&board), evaluate(&mirrored), "{}", fen);
+    }
+}
+
+#[test]
+fn centralized_pieces_are_better() {
+    let centered = Board::from_fen("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1").unwrap();
+    let cornered = Board::from_fen("4k3/8/8/8/8/8/8/N3K3 w - - 0 1").unwrap();
+    assert!(evaluate(&centered) > evaluate(&cornered));
+}
+
+#[test]
+fn weak_pawns_are_penalized() {
+    let healthy = Board::from_fen("4k3/pp6/8/8/8/8/PP6/4K3 w - - 0 1").unwrap();
+    let entry = evaluate_pawns(&healthy);
+    assert_eq!((entry.midgame, entry.endgame), (0, 0));
+
+    // Doubled and isolated on the a-file
+    let weak = Board::from_fen("4k3/pp6/8/8/8/P7/P7/4K3 w - - 0 1").unwrap();
+    let entry = evaluate_pawns(&weak);
+    assert!(entry.midgame < 0 && entry.endgame < 0);
+}
+
+#[test]
+fn passed_pawns_are_recognized() {
+    let passed = Board::from_fen("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1").unwrap();
+    assert_eq!(evaluate_pawns(&passed).passed, [bit(35), 0]);
+    assert!(evaluate_pawns(&passed).endgame > 0);
+
+    // Pawns on adjacent files can stop each other
+    let stopped = Board::from_fen("4k3/2p5/8/3P4/8/8/8/4K3 w - - 0 1").unwrap();
+    assert_eq!(evaluate_pawns(&stopped).passed, [0, 0]);
+}
+
+#[test]
+fn pawns_on_the_back_ranks_are_ignored() {
+    // No FEN describes such a board, but it can still be set up by hand
+    let mut board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    board.set(&"a1".parse().unwrap(), Piece::PAWN | Piece::BLACK);
+    board.set(&"h8".parse().unwrap(), Piece::PAWN | Piece::WHITE);
+    board.set(&"b2".parse().unwrap(), Piece::PAWN | Piece::BLACK);
+
+    let entry = evaluate_pawns(&board);
+    assert_eq!(entry.passed, [0, bit(9)]);
+    evaluate(&board);
+}
+
+#[test]
+fn exposed_kings_are_penalized() {
+    let sheltered = Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1").unwrap();
+    assert_eq!(evaluate_king_safety(&sheltered), 0);
+
+    let open = Board::from_fen("6k1/5ppp/8/8/8/8/5P2/6K1 w - - 0 1").unwrap();
+    assert!(evaluate_king_safety(&open) < 0);
+
+    // The queen and the knight both hit the squares around the black king
+    let attacked = Board::from_fen("6k1/5ppp/8/6NQ/8/8/5PPP/6K1 w - - 0 1").unwrap();
+    assert!(evaluate_king_safety(&attacked) > 0);
+}
@@ -0,0 +1,104 @@
+//! Reading and writing positions in Forsyth-Edwards Notation.
+
+use vite_wasm_functions::board::{Board, CastlingRights, Player};
+use vite_wasm_functions::fen::{FenError, STARTING_FEN};
+
+#[test]
+fn round_trip() {
+    for fen in [
+        STARTING_FEN,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 37 52",
+        "4k3/8/8/8/8/8/8/4K2R w K - 0 1",
+    ] {
+        assert_eq!(Board::from_fen(fen).unwrap().to_fen(), fen);
+    }
+
+    assert_eq!(Board::default().to_fen(), STARTING_FEN);
+}
+
+#[test]
+fn fields_are_read() {
+    let board =
+        Board::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w Kq f6 4 3").unwrap();
+    assert_eq!(board.turn, Player::White);
+    assert_eq!(
+        board.castling_rights(),
+        CastlingRights::WHITE_KINGSIDE | CastlingRights::BLACK_QUEENSIDE
+    );
+    assert_eq!(board.en_passant(), Some("f6".parse().unwrap()));
+    assert_eq!(board.halfmove_clock(), 4);
+    assert_eq!(board.fullmove_number(), 3);
+    assert_eq!(board.hash(), board.compute_hash());
+}
+
+#[test]
+fn move_counters_are_optional() {
+    let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 b - -").unwrap();
+    assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/4K3 b - - 0 1");
+}
+
+#[test]
+fn invalid_fen() {
+    for (fen, error) in [
+        ("", FenError::FieldCount(0)),
+        ("8/8/8/8/8/8/8/8 w - - 0 1 extra", FenError::FieldCount(7)),
+        ("8/8/8/8/8/8/8 w - - 0 1", FenError::RankCount(7)),
+        ("8/8/8/8/8/8/8/7 w - - 0 1", FenError::RankLength(8)),
+        ("9/8/8/8/8/8/8/8 w - - 0 1", FenError::InvalidPiece('9')),
+        ("8/8/8/8/8/8/8/4K4 w - - 0 1", FenError::RankLength(8)),
+        ("8/8/8/8/8/8/8/4X3 w - - 0 1", FenError::InvalidPiece('X')),
+        (
+            "8/8/8/8/8/8/8/8 x - - 0 1",
+            FenError::InvalidSideToMove("x".to_string()),
+        ),
+        (
+            "8/8/8/8/8/8/8/8 w KX - 0 1",
+            FenError::InvalidCastling("KX".to_string()),
+        ),
+        (
+            "8/8/8/8/8/8/8/8 w - e4 0 1",
+            FenError::InvalidEnPassant("e4".to_string()),
+        ),
+        // The square behind a pawn of the side to move, or without a pawn in front
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1",
+            FenError::InvalidEnPassant("e3".to_string()),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 1",
+            FenError::InvalidEnPassant("e6".to_string()),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq d3 0 1",
+            FenError::InvalidEnPassant("d3".to_string()),
+        ),
+        (
+            "8/8/8/8/8/8/8/8 w - - -1 1",
+            FenError::InvalidHalfmoveClock("-1".to_string()),
+        ),
+        (
+            "8/8/8/8/8/8/8/8 w - - 0 0",
+            FenError::InvalidFullmoveNumber("0".to_string()),
+        ),
+        (
+            "8/8/8/8/8/8/8/4K2R w - - 0 1",
+            FenError::ImpossiblePosition("Black has 0 kings".to_string()),
+        ),
+        (
+            "4k3/8/8/8/8/8/8/3KK3 w - - 0 1",
+            FenError::ImpossiblePosition("White has 2 kings".to_string()),
+        ),
+        (
+            "4k3/8/8/8/8/8/1p6/p3K3 w - - 0 1",
+            FenError::ImpossiblePosition("pawn on a1".to_string()),
+        ),
+        (
+            "P3k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            FenError::ImpossiblePosition("pawn on a8".to_string()),
+        ),
+    ] {
+        assert_eq!(Board::from_fen(fen).unwrap_err(), error, "{}", fen);
+    }
+}
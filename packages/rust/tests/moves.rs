@@ -0,0 +1,202 @@
+//! Move generation has to produce exactly the legal moves of a position.
+
+use vite_wasm_functions::board::{Board, CastlingRights};
+use vite_wasm_functions::moves::Move;
+use vite_wasm_functions::piece::Piece;
+
+/// The legal moves of the position in UCI notation, sorted.
+fn legal_moves(fen: &str) -> Vec<String> {
+    let board = Board::from_fen(fen).unwrap();
+    let mut moves: Vec<String> = board
+        .generate_all_moves()
+        .iter()
+        .map(|move_| move_.to_uci())
+        .collect();
+    moves.sort();
+    moves
+}
+
+#[test]
+fn pinned_piece_stays_on_the_pin() {
+    // The knight cannot move at all without exposing the king
+    let moves = legal_moves("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1");
+    assert!(moves.iter().all(|move_| !move_.starts_with("e2")));
+
+    // The rook may still move along the file, up to capturing the pinner
+    let moves = legal_moves("4k3/4r3/8/8/8/8/4R3/4K3 w - - 0 1");
+    let rook_moves: Vec<&String> = moves
+        .iter()
+        .filter(|move_| move_.starts_with("e2"))
+        .collect();
+    assert_eq!(rook_moves, ["e2e3", "e2e4", "e2e5", "e2e6", "e2e7"]);
+}
+
+#[test]
+fn king_cannot_move_into_check() {
+    // The rook covers the first and second rank and the d-file, but is
+    // undefended and may be taken
+    assert_eq!(
+        legal_moves("4k3/8/8/8/8/8/3r4/4K3 w - - 0 1"),
+        ["e1d2", "e1f1"]
+    );
+}
+
+#[test]
+fn check_has_to_be_answered() {
+    // The rook cannot help, the king has to step off the file
+    assert_eq!(
+        legal_moves("4k3/4r3/8/8/8/8/8/R3K3 w - - 0 1"),
+        ["e1d1", "e1d2", "e1f1", "e1f2"]
+    );
+
+    // Blocking and capturing the checker are fine as well
+    assert_eq!(
+        legal_moves("4k3/4r3/8/8/7Q/8/8/4K3 w - - 0 1"),
+        ["e1d1", "e1d2", "e1f1", "e1f2", "h4e4", "h4e7"]
+    );
+}
+
+#[test]
+fn castling_needs_rights_and_safe_squares() {
+    let moves = legal_moves("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    assert!(moves.contains(&"e1g1".to_string()));
+    assert!(moves.contains(&"e1c1".to_string()));
+
+    // Without the rights there is no castling
+    let moves = legal_moves("r3k2r/8/8/8/8/8/8/R3K2R w kq - 0 1");
+    assert!(!moves.contains(&"e1g1".to_string()));
+    assert!(!moves.contains(&"e1c1".to_string()));
+
+    // The king may not pass through an attacked square
+    let moves = legal_moves("r3k2r/8/8/8/8/8/5r2/R3K2R w KQkq - 0 1");
+    assert!(!moves.contains(&"e1g1".to_string()));
+    assert!(moves.contains(&"e1c1".to_string()));
+
+    // Nor castle out of check
+    let moves = legal_moves("r3k2r/8/8/8/8/8/4r3/R3K2R w KQkq - 0 1");
+    assert!(!moves.contains(&"e1g1".to_string()));
+    assert!(!moves.contains(&"e1c1".to_string()));
+
+    // The squares between king and rook have to be empty
+    let moves = legal_moves("r3k2r/8/8/8/8/8/8/RN2K1NR w KQkq - 0 1");
+    assert!(!moves.contains(&"e1g1".to_string()));
+    assert!(!moves.contains(&"e1c1".to_string()));
+}
+
+#[test]
+fn castling_moves_the_rook_and_revokes_rights() {
+    let mut board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+    Move::from_uci("e1g1").unwrap().execute(&mut board);
+    assert_eq!(board.to_fen(), "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1");
+
+    Move::from_uci("e8c8").unwrap().execute(&mut board);
+    assert_eq!(board.to_fen(), "2kr3r/8/8/8/8/8/8/R4RK1 w - - 2 2");
+}
+
+#[test]
+fn rook_moves_and_captures_revoke_castling_rights() {
+    let mut board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+
+    // Moving a rook only gives up its own side
+    Move::from_uci("a1a2").unwrap().execute(&mut board);
+    assert_eq!(
+        board.castling_rights(),
+        CastlingRights::WHITE_KINGSIDE | CastlingRights::BLACK
+    );
+
+    // Losing a rook to a capture does as well
+    Move::from_uci("h8h1").unwrap().execute(&mut board);
+    assert_eq!(board.castling_rights(), CastlingRights::BLACK_QUEENSIDE);
+
+    // And a king move gives up both
+    Move::from_uci("e1f2").unwrap().execute(&mut board);
+    Move::from_uci("e8d8").unwrap().execute(&mut board);
+    assert_eq!(board.castling_rights(), CastlingRights::empty());
+}
+
+#[test]
+fn en_passant_capture() {
+    let mut board = Board::from_fen("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1").unwrap();
+    Move::from_uci("e2e4").unwrap().execute(&mut board);
+    assert_eq!(board.en_passant(), Some("e3".parse().unwrap()));
+    Move::from_uci("e8f7").unwrap().execute(&mut board);
+    assert_eq!(board.en_passant(), None);
+
+    // Without an enemy pawn beside it, a double push leaves no en-passant square
+    let mut board = Board::default();
+    Move::from_uci("e2e4").unwrap().execute(&mut board);
+    assert_eq!(board.en_passant(), None);
+
+    let fen = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1";
+    assert!(legal_moves(fen).contains(&"e5d6".to_string()));
+    let mut board = Board::from_fen(fen).unwrap();
+    Move::from_uci("e5d6").unwrap().execute(&mut board);
+    assert_eq!(board.to_fen(), "4k3/8/3P4/8/8/8/8/4K3 b - - 0 1");
+
+    // Only right after the double push
+    assert!(!legal_moves("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 1").contains(&"e5d6".to_string()));
+}
+
+#[test]
+fn en_passant_cannot_expose_the_king() {
+    // Both pawns leave the fifth rank, opening it for the rook
+    assert!(!legal_moves("8/8/8/K2pP2r/8/8/8/4k3 w - d6 0 1").contains(&"e5d6".to_string()));
+}
+
+#[test]
+fn promotion_offers_every_piece() {
+    let moves = legal_moves("3r4/4P3/8/8/8/8/k7/4K3 w - - 0 1");
+    let promotions: Vec<&String> = moves
+        .iter()
+        .filter(|move_| move_.starts_with("e7"))
+        .collect();
+    assert_eq!(
+        promotions,
+        ["e7d8b", "e7d8n", "e7d8q", "e7d8r", "e7e8b", "e7e8n", "e7e8q", "e7e8r"]
+    );
+
+    let mut board = Board::from_fen("3r4/4P3/8/8/8/8/k7/4K3 w - - 0 1").unwrap();
+    Move::from_uci("e7d8n").unwrap().execute(&mut board);
+    assert_eq!(board.to_fen(), "3N4/8/8/8/8/8/k7/4K3 b - - 0 1");
+
+    let mut board = Board::from_fen("4k3/8/8/8/8/8/p7/4K3 b - - 0 1").unwrap();
+    Move::from_uci("a2a1q").unwrap().execute(&mut board);
+    assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/q3K3 w - - 0 2");
+}
+
+#[test]
+fn moves_need_a_piece_of_the_side_to_move() {
+    let board = Board::default();
+    for uci in ["e3e4", "e7e5", "d4d5q"] {
+        let move_ = Move::from_uci(uci).unwrap();
+        assert!(!board.is_legal(&move_), "{}", uci);
+    }
+    assert!(board.generate_moves_for("e7".parse().unwrap()).is_empty());
+    assert!(board.generate_moves_for("e4".parse().unwrap()).is_empty());
+}
+
+#[test]
+fn moves_from_an_empty_square_keep_the_board_consistent() {
+    // Nothing checks the move before it is played, e.g. `Move.do` from JS
+    let mut board = Board::default();
+    let before = board.clone();
+    let move_ = Move::from_uci("e4e5q").unwrap();
+    let undo = board.make_move(&move_);
+    assert_eq!(board.hash(), board.compute_hash());
+    board.unmake_move(&move_, undo);
+    assert_eq!(board.to_fen(), before.to_fen());
+    assert_eq!(board.hash(), before.hash());
+
+    // A promotion onto an occupied square takes the piece, and gives it back
+    let move_ = Move::from_uci("e4e7q").unwrap();
+    let undo = board.make_move(&move_);
+    assert_eq!(board.hash(), board.compute_hash());
+    board.unmake_move(&move_, undo);
+    assert_eq!(board.to_fen(), before.to_fen());
+
+    let mut board = Board::empty();
+    board.set(&"e4".parse().unwrap(), Piece::QUEEN);
+    board.set(&"d4".parse().unwrap(), Piece::WHITE);
+    assert_eq!(board.occupied(), 0);
+    assert_eq!(board.to_fen(), Board::empty().to_fen());
+}
@@ -0,0 +1,65 @@
+//! Squares in algebraic notation and moves in UCI notation.
+
+use vite_wasm_functions::moves::{Move, UciMoveError};
+use vite_wasm_functions::piece::Piece;
+use vite_wasm_functions::pos::{ParsePositionError, Position};
+
+#[test]
+fn squares() {
+    assert_eq!("a1".parse(), Ok(Position::new(0, 0)));
+    assert_eq!("h8".parse(), Ok(Position::new(7, 7)));
+    assert_eq!("E4".parse(), Ok(Position::new(4, 3)));
+
+    for y in 0..8 {
+        for x in 0..8 {
+            let pos = Position::new(x, y);
+            assert_eq!(pos.to_algebraic().parse(), Ok(pos));
+        }
+    }
+
+    for invalid in ["", "e", "e9", "i1", "e0", "e44", "4e"] {
+        assert_eq!(
+            invalid.parse::<Position>(),
+            Err(ParsePositionError(invalid.to_string()))
+        );
+    }
+}
+
+#[test]
+fn uci_moves() {
+    let move_ = Move::from_uci("e2e4").unwrap();
+    assert_eq!(
+        move_,
+        Move::new("e2".parse().unwrap(), "e4".parse().unwrap())
+    );
+    assert_eq!(move_.to_uci(), "e2e4");
+
+    let move_ = Move::from_uci("a7a8n").unwrap();
+    assert_eq!(move_.promotion(), Some(Piece::KNIGHT));
+    assert_eq!(move_.to_uci(), "a7a8n");
+
+    // Castling is written as the king move
+    assert_eq!(Move::from_uci("e1g1").unwrap().to_uci(), "e1g1");
+}
+
+#[test]
+fn invalid_uci_moves() {
+    for (uci, error) in [
+        ("e2e", UciMoveError::InvalidLength("e2e".to_string())),
+        ("e2e4qq", UciMoveError::InvalidLength("e2e4qq".to_string())),
+        ("é2e4", UciMoveError::InvalidLength("é2e4".to_string())),
+        (
+            "e9e4",
+            UciMoveError::InvalidSquare(ParsePositionError("e9".to_string())),
+        ),
+        (
+            "e2z4",
+            UciMoveError::InvalidSquare(ParsePositionError("z4".to_string())),
+        ),
+        ("a7a8k", UciMoveError::InvalidPromotion('k')),
+        ("a7a8p", UciMoveError::InvalidPromotion('p')),
+        ("a7a8x", UciMoveError::InvalidPromotion('x')),
+    ] {
+        assert_eq!(Move::from_uci(uci), Err(error), "{}", uci);
+    }
+}
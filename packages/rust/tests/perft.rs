@@ -0,0 +1,131 @@
+//! Perft counts of the well known test positions from
+//! <https://www.chessprogramming.org/Perft_Results>.
+//!
+//! The deeper counts take a while and are ignored by default, run them with
+//! `cargo test --release -- --ignored`.
+
+use vite_wasm_functions::board::Board;
+use vite_wasm_functions::piece::Piece;
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+const POSITION_4_MIRRORED: &str =
+    "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1";
+const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+const POSITION_6: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+fn assert_perft(fen: &str, expected: &[u64]) {
+    let board = Board::from_fen(fen).unwrap();
+    for (depth, &nodes) in expected.iter().enumerate() {
+        assert_eq!(
+            board.perft(depth + 1),
+            nodes,
+            "{} at depth {}",
+            fen,
+            depth + 1
+        );
+    }
+}
+
+#[test]
+fn start_position() {
+    assert_perft(
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        &[20, 400, 8902],
+    );
+}
+
+#[test]
+fn kiwipete() {
+    assert_perft(KIWIPETE, &[48, 2039]);
+}
+
+#[test]
+fn position_3() {
+    assert_perft(POSITION_3, &[14, 191, 2812]);
+}
+
+#[test]
+fn position_4() {
+    assert_perft(POSITION_4, &[6, 264, 9467]);
+    assert_perft(POSITION_4_MIRRORED, &[6, 264, 9467]);
+}
+
+#[test]
+fn position_5() {
+    assert_perft(POSITION_5, &[44, 1486]);
+}
+
+#[test]
+fn position_6() {
+    assert_perft(POSITION_6, &[46, 2079]);
+}
+
+#[test]
+fn divide_sums_up_to_perft() {
+    let board = Board::from_fen(KIWIPETE).unwrap();
+    let divide = board.divide(2);
+    assert_eq!(divide.len(), 48);
+    assert_eq!(divide.iter().map(|(_, nodes)| nodes).sum::<u64>(), 2039);
+}
+
+#[test]
+fn captures_are_the_capturing_and_promoting_moves() {
+    for fen in [KIWIPETE, POSITION_3, POSITION_4, POSITION_5, POSITION_6] {
+        let board = Board::from_fen(fen).unwrap();
+        let mut expected: Vec<String> = board
+            .generate_all_moves()
+            .into_iter()
+            .filter(|m| {
+                board[m.get_end()] != Piece::NONE || m.is_en_passant(&board) || m.is_promotion()
+            })
+            .map(|m| m.to_uci())
+            .collect();
+        let mut captures: Vec<String> = board
+            .generate_captures()
+            .into_iter()
+            .map(|m| m.to_uci())
+            .collect();
+
+        expected.sort();
+        captures.sort();
+        assert_eq!(captures, expected, "{}", fen);
+    }
+}
+
+#[test]
+#[ignore]
+fn start_position_deep() {
+    assert_eq!(Board::default().perft(5), 4_865_609);
+}
+
+#[test]
+#[ignore]
+fn kiwipete_deep() {
+    assert_eq!(Board::from_fen(KIWIPETE).unwrap().perft(4), 4_085_603);
+}
+
+#[test]
+#[ignore]
+fn position_3_deep() {
+    assert_eq!(Board::from_fen(POSITION_3).unwrap().perft(5), 674_624);
+}
+
+#[test]
+#[ignore]
+fn position_4_deep() {
+    assert_eq!(Board::from_fen(POSITION_4).unwrap().perft(4), 422_333);
+}
+
+#[test]
+#[ignore]
+fn position_5_deep() {
+    assert_eq!(Board::from_fen(POSITION_5).unwrap().perft(4), 2_103_487);
+}
+
+#[test]
+#[ignore]
+fn position_6_deep() {
+    assert_eq!(Board::from_fen(POSITION_6).unwrap().perft(4), 3_894_594);
+}
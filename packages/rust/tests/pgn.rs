@@ -0,0 +1,209 @@
+//! Reading and writing game records in Portable Game Notation.
+
+use vite_wasm_functions::moves::Move;
+use vite_wasm_functions::pgn::{Game, PgnError};
+use vite_wasm_functions::san::SanError;
+
+const ANNOTATED: &str = r#"[Event "Casual Game"]
+[Site "Berlin GER"]
+[Date "1852.??.??"]
+[Round "?"]
+[White "Adolf Anderssen"]
+[Black "Jean Dufresne"]
+[Result "1-0"]
+
+{Evergreen} 1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. b4!? Bxb4 5. c3 Ba5 6. d4 exd4
+7. O-O d3 8. Qb3 Qf6 9. e5 Qg6 10. Re1 Nge7 11. Ba3 b5 $6 (11... O-O 12. Bxe7)
+12. Qxb5 Rb8 13. Qa4 Bb6 14. Nbd2 Bb7 15. Ne4 Qf5? 16. Bxd3 Qh5 17. Nf6+ gxf6
+18. exf6 Rg8 19. Rad1!! Qxf3 20. Rxe7+ Nxe7 21. Qxd7+ Kxd7 22. Bf5+ Ke8
+23. Bd7+ Kf8 24. Bxe7# 1-0
+"#;
+
+#[test]
+fn round_trip() {
+    let game = Game::from_pgn(ANNOTATED).unwrap();
+    let pgn = game.to_pgn();
+    assert_eq!(Game::from_pgn(&pgn).unwrap().to_pgn(), pgn);
+
+    assert!(pgn.starts_with("[Event \"Casual Game\"]\n[Site \"Berlin GER\"]\n"));
+    assert!(pgn.lines().all(|line| line.len() <= 80));
+    assert!(pgn.ends_with("24. Bxe7# 1-0\n"));
+}
+
+#[test]
+fn written_game() {
+    let mut game = Game::new();
+    game.set_tag("White", "Fool");
+    for uci in ["f2f3", "e7e5", "g2g4", "d8h4"] {
+        game.push(Move::from_uci(uci).unwrap()).unwrap();
+    }
+
+    assert_eq!(
+        game.to_pgn(),
+        "[Event \"?\"]\n[Site \"?\"]\n[Date \"?\"]\n[Round \"?\"]\n\
+         [White \"Fool\"]\n[Black \"?\"]\n[Result \"0-1\"]\n\n\
+         1. f3 e5 2. g4 Qh4# 0-1\n"
+    );
+}
+
+#[test]
+fn illegal_moves_are_not_pushed() {
+    let mut game = Game::new();
+    let move_ = Move::from_uci("e2e5").unwrap();
+    assert_eq!(game.push(move_.clone()), Err(PgnError::IllegalMove(move_)));
+
+    // Nor are moves of the side not to move
+    let move_ = Move::from_uci("e7e5").unwrap();
+    assert_eq!(game.push(move_.clone()), Err(PgnError::IllegalMove(move_)));
+
+    assert_eq!(game.mainline().count(), 0);
+    assert!(game.to_pgn().ends_with("\n\n*\n"));
+}
+
+#[test]
+fn tags() {
+    let game = Game::from_pgn(ANNOTATED).unwrap();
+    assert_eq!(game.tag("White"), Some("Adolf Anderssen"));
+    assert_eq!(game.tag("Date"), Some("1852.??.??"));
+    assert_eq!(game.tag("Annotator"), None);
+    assert_eq!(game.tags().len(), 7);
+
+    // Quotes and backslashes are escaped
+    let game = Game::from_pgn("[Event \"The \\\"Immortal\\\" \\\\ Game\"]\n*").unwrap();
+    assert_eq!(game.tag("Event"), Some("The \"Immortal\" \\ Game"));
+    assert!(game
+        .to_pgn()
+        .starts_with("[Event \"The \\\"Immortal\\\" \\\\ Game\"]\n"));
+
+    // A position set up from a FEN
+    let fen = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+    let game = Game::from_pgn(&format!("[FEN \"{}\"]\n1. e4 *", fen)).unwrap();
+    assert_eq!(game.start().to_fen(), fen);
+    assert_eq!(game.board().to_fen(), "4k3/8/8/8/4P3/8/8/4K3 b - - 0 1");
+}
+
+#[test]
+fn comments_and_nags() {
+    let game = Game::from_pgn(ANNOTATED).unwrap();
+    let moves = game.moves();
+    assert_eq!(moves.len(), 47);
+    assert_eq!(moves[0].starting_comment.as_deref(), Some("Evergreen"));
+
+    // Suffix annotations and explicit NAGs
+    assert_eq!(moves[6].san, "b4");
+    assert_eq!(moves[6].nags, [5]);
+    assert_eq!(moves[21].nags, [6]);
+    assert_eq!(moves[29].nags, [2]);
+    assert_eq!(moves[36].nags, [3]);
+
+    let game = Game::from_pgn("1. e4 {best by test} ; rest of line\n e5 *").unwrap();
+    assert_eq!(
+        game.moves()[0].comment.as_deref(),
+        Some("best by test rest of line")
+    );
+    assert!(game
+        .to_pgn()
+        .contains("1. e4 {best by test rest of line} 1... e5 *"));
+}
+
+#[test]
+fn variations() {
+    let game = Game::from_pgn(ANNOTATED).unwrap();
+    let variations = &game.moves()[21].variations;
+    assert_eq!(variations.len(), 1);
+    let sans: Vec<&str> = variations[0].iter().map(|node| node.san.as_str()).collect();
+    assert_eq!(sans, ["O-O", "Bxe7"]);
+    assert!(game.to_pgn().contains("b5 $6 (11... O-O 12. Bxe7) 12."));
+
+    // Variations nest and only change the main line at their start
+    let game = Game::from_pgn("1. e4 (1. d4 d5 (1... Nf6 2. c4) 2. c4) 1... e5 *").unwrap();
+    assert_eq!(game.mainline().count(), 2);
+    let variation = &game.moves()[0].variations[0];
+    assert_eq!(variation[1].variations[0][0].san, "Nf6");
+    assert_eq!(
+        game.board().to_fen(),
+        "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2"
+    );
+}
+
+#[test]
+fn result_tokens() {
+    for result in ["1-0", "0-1", "1/2-1/2", "*"] {
+        let game = Game::from_pgn(&format!("1. e4 e5 {}", result)).unwrap();
+        assert_eq!(game.result(), result);
+        assert!(game.to_pgn().ends_with(&format!("1. e4 e5 {}\n", result)));
+    }
+
+    // Without a result token the game is still running
+    let game = Game::from_pgn("1. e4 e5").unwrap();
+    assert_eq!(game.result(), "*");
+
+    // Several games follow each other, with or without a result in between
+    let games =
+        Game::all_from_pgn("1. e4 1-0\n\n[Event \"Next\"]\n1. d4\n[Event \"Last\"]\n*\n").unwrap();
+    assert_eq!(games.len(), 3);
+    assert_eq!(games[1].mainline().count(), 1);
+    assert_eq!(games[2].tag("Event"), Some("Last"));
+}
+
+#[test]
+fn malformed_input() {
+    for (pgn, error) in [
+        ("", PgnError::NoGame),
+        (
+            "[Event \"Test\"\n1. e4 *",
+            PgnError::UnterminatedTag { line: 1 },
+        ),
+        ("[Event Test]\n*", PgnError::InvalidTag { line: 1 }),
+        (
+            "1. e4 {unfinished\n\n*",
+            PgnError::UnterminatedComment { line: 1 },
+        ),
+        ("(1. d4) 1. e4 *", PgnError::UnexpectedVariation { line: 1 }),
+        ("1. e4 ) *", PgnError::UnexpectedVariation { line: 1 }),
+        (
+            "1. e4 (1. d4\n*",
+            PgnError::UnterminatedVariation { line: 2 },
+        ),
+        (
+            "$1 1. e4 *",
+            PgnError::UnexpectedToken {
+                line: 1,
+                token: "$1".to_string(),
+            },
+        ),
+        (
+            "1. e4?? e5 1. e4!x *",
+            PgnError::UnexpectedToken {
+                line: 1,
+                token: "e4!x".to_string(),
+            },
+        ),
+        (
+            "1. e5 *",
+            PgnError::InvalidMove {
+                line: 1,
+                error: SanError::Illegal("e5".to_string()),
+            },
+        ),
+        (
+            "[FEN \"4k3/8/8/8/8/8/4K3/R6R w - - 0 1\"]\n1. Rd1 *",
+            PgnError::InvalidMove {
+                line: 2,
+                error: SanError::Ambiguous("Rd1".to_string()),
+            },
+        ),
+        (
+            "1. e4 Zz9 *",
+            PgnError::InvalidMove {
+                line: 1,
+                error: SanError::Invalid("Zz9".to_string()),
+            },
+        ),
+    ] {
+        assert_eq!(Game::from_pgn(pgn).unwrap_err(), error, "{}", pgn);
+    }
+
+    let error = Game::from_pgn("[FEN \"not a fen\"]\n*").unwrap_err();
+    assert!(matches!(error, PgnError::InvalidFen(_)));
+}
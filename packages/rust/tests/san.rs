@@ -0,0 +1,126 @@
+//! Formatting and parsing moves in Standard Algebraic Notation.
+
+use vite_wasm_functions::board::Board;
+use vite_wasm_functions::moves::Move;
+use vite_wasm_functions::san::SanError;
+
+fn san(fen: &str, uci: &str) -> String {
+    let board = Board::from_fen(fen).unwrap();
+    Move::from_uci(uci).unwrap().to_san(&board)
+}
+
+fn from_san(fen: &str, san: &str) -> Result<String, SanError> {
+    let board = Board::from_fen(fen).unwrap();
+    Move::from_san(&board, san).map(|move_| move_.to_uci())
+}
+
+#[test]
+fn pieces_and_captures() {
+    let board = Board::default();
+    assert_eq!(Move::from_uci("e2e4").unwrap().to_san(&board), "e4");
+    assert_eq!(Move::from_uci("g1f3").unwrap().to_san(&board), "Nf3");
+
+    let fen = "4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1";
+    assert_eq!(san(fen, "e4d5"), "exd5");
+    assert_eq!(san("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), "exd6");
+    assert_eq!(san("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1", "d1d5"), "Rxd5");
+}
+
+#[test]
+fn disambiguation() {
+    // By file, by rank, and by both when neither is enough
+    let fen = "4k3/8/8/8/8/8/4K3/R6R w - - 0 1";
+    assert_eq!(san(fen, "a1d1"), "Rad1");
+    let fen = "R7/8/4k3/8/8/8/4K3/R7 w - - 0 1";
+    assert_eq!(san(fen, "a1a4"), "R1a4");
+    let fen = "k7/8/8/8/8/2Q1Q3/8/2Q1K3 w - - 0 1";
+    assert_eq!(san(fen, "c3d2"), "Qc3d2");
+
+    // A pinned piece does not count as a second candidate
+    let fen = "4k3/4r3/8/8/8/2N5/4N3/4K3 w - - 0 1";
+    assert_eq!(san(fen, "c3d4"), "Nd4");
+}
+
+#[test]
+fn check_and_mate_suffixes() {
+    assert_eq!(san("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a8"), "Ra8+");
+    assert_eq!(san("4k3/8/4K3/8/8/8/8/R7 w - - 0 1", "a1a8"), "Ra8#");
+
+    let mut board = Board::default();
+    let mut sans = Vec::new();
+    for uci in ["f2f3", "e7e5", "g2g4", "d8h4"] {
+        let move_ = Move::from_uci(uci).unwrap();
+        sans.push(move_.to_san(&board));
+        move_.execute(&mut board);
+    }
+    assert_eq!(sans, ["f3", "e5", "g4", "Qh4#"]);
+}
+
+#[test]
+fn promotion() {
+    let fen = "3r4/4P3/8/8/8/8/k7/4K3 w - - 0 1";
+    assert_eq!(san(fen, "e7e8q"), "e8=Q");
+    assert_eq!(san(fen, "e7e8n"), "e8=N");
+    assert_eq!(san(fen, "e7d8r"), "exd8=R");
+    assert_eq!(san("k7/4P3/1K6/8/8/8/8/8 w - - 0 1", "e7e8q"), "e8=Q#");
+
+    assert_eq!(from_san(fen, "e8=Q").unwrap(), "e7e8q");
+    assert_eq!(from_san(fen, "e8N").unwrap(), "e7e8n");
+    assert_eq!(from_san(fen, "exd8=B+").unwrap(), "e7d8b");
+}
+
+#[test]
+fn castling() {
+    let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+    assert_eq!(san(fen, "e1g1"), "O-O");
+    assert_eq!(san(fen, "e1c1"), "O-O-O");
+    assert_eq!(san("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "e8c8"), "O-O-O");
+    assert_eq!(san("5k2/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1"), "O-O+");
+
+    assert_eq!(from_san(fen, "O-O").unwrap(), "e1g1");
+    assert_eq!(from_san(fen, "0-0-0").unwrap(), "e1c1");
+    assert_eq!(
+        from_san("r3k2r/8/8/8/8/8/8/R3K2R w kq - 0 1", "O-O"),
+        Err(SanError::Illegal("O-O".to_string()))
+    );
+}
+
+#[test]
+fn parsing() {
+    let fen = "4k3/8/8/8/8/8/4K3/R6R w - - 0 1";
+    assert_eq!(from_san(fen, "Rad1").unwrap(), "a1d1");
+    assert_eq!(
+        from_san(fen, "Rbd1").unwrap_err(),
+        SanError::Illegal("Rbd1".to_string())
+    );
+    assert_eq!(
+        from_san(fen, "Rd1").unwrap_err(),
+        SanError::Ambiguous("Rd1".to_string())
+    );
+    assert_eq!(
+        from_san(fen, "Rd9").unwrap_err(),
+        SanError::Invalid("Rd9".to_string())
+    );
+    assert_eq!(
+        from_san(fen, "").unwrap_err(),
+        SanError::Invalid("".to_string())
+    );
+
+    // Annotations and the en-passant marker are ignored
+    assert_eq!(from_san(fen, "Rhf1!?").unwrap(), "h1f1");
+    assert_eq!(
+        from_san("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "exd6e.p.").unwrap(),
+        "e5d6"
+    );
+}
+
+#[test]
+fn round_trip() {
+    let board =
+        Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+            .unwrap();
+    for move_ in board.generate_all_moves() {
+        let san = move_.to_san(&board);
+        assert_eq!(Move::from_san(&board, &san), Ok(move_), "{}", san);
+    }
+}
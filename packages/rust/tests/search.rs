@@ -0,0 +1,164 @@
+//! The search results have to describe what the search found consistently.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use vite_wasm_functions::ai::{Ai, SearchLimits, DEFAULT_MOVETIME};
+use vite_wasm_functions::board::Board;
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+#[test]
+fn principal_variation_is_a_legal_line() {
+    for board in [Board::default(), Board::from_fen(KIWIPETE).unwrap()] {
+        let result = Ai::new().search(&board, &SearchLimits::depth(4)).unwrap();
+
+        assert_eq!(result.pv.first(), Some(&result.best_move));
+        assert_eq!(result.pv.get(1), result.ponder_move.as_ref());
+        assert!(result.pv.len() <= 4);
+        assert!(result.seldepth >= result.depth);
+        assert_eq!(
+            result.nps,
+            (result.nodes * 1000).checked_div(result.time).unwrap_or(0)
+        );
+
+        let mut board = board.clone();
+        for move_ in &result.pv {
+            assert!(
+                board.generate_all_moves().contains(move_),
+                "{}",
+                move_.to_uci()
+            );
+            move_.execute(&mut board);
+        }
+    }
+}
+
+#[test]
+fn mating_line_ends_in_mate() {
+    let mut board = Board::from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap();
+    let result = Ai::new().search(&board, &SearchLimits::depth(3)).unwrap();
+
+    assert_eq!(result.mate, Some(2));
+    assert_eq!(result.pv.len(), 3);
+    for move_ in &result.pv {
+        move_.execute(&mut board);
+    }
+    assert!(board.is_game_over());
+}
+
+#[test]
+fn search_ends_once_mate_is_proven() {
+    let board = Board::from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap();
+    let result = Ai::new().search(&board, &SearchLimits::default()).unwrap();
+    assert_eq!(result.mate, Some(2));
+    assert_eq!(result.depth, 3);
+
+    // The side getting mated gives up as well
+    let board = Board::from_fen("k7/8/1K6/8/8/8/8/6R1 b - - 0 1").unwrap();
+    let result = Ai::new().search(&board, &SearchLimits::default()).unwrap();
+    assert_eq!(result.mate, Some(-1));
+    assert_eq!(result.depth, 2);
+}
+
+#[test]
+fn multi_pv_ranks_distinct_moves() {
+    let board = Board::default();
+    let mut ai = Ai::new();
+    ai.set_multi_pv(3);
+    let result = ai.search(&board, &SearchLimits::depth(3)).unwrap();
+
+    assert_eq!(result.lines.len(), 3);
+    assert_eq!(result.lines[0].pv, result.pv);
+    assert_eq!(result.lines[0].score, result.score);
+    assert!(result
+        .lines
+        .windows(2)
+        .all(|pair| pair[0].score >= pair[1].score));
+    assert!(result.lines[1].pv[0] != result.lines[0].pv[0]);
+    assert!(result.lines[2].pv[0] != result.lines[1].pv[0]);
+    assert!(result.lines[2].pv[0] != result.lines[0].pv[0]);
+
+    // There are never more lines than legal moves
+    let board = Board::from_fen("k7/8/1K6/8/8/8/8/7R b - - 0 1").unwrap();
+    let mut ai = Ai::new();
+    ai.set_multi_pv(3);
+    let result = ai.search(&board, &SearchLimits::depth(2)).unwrap();
+    assert_eq!(result.lines.len(), 1);
+}
+
+#[test]
+fn depth_limit() {
+    let board = Board::from_fen(KIWIPETE).unwrap();
+    let mut depths = Vec::new();
+    let result = Ai::new()
+        .search_with(&board, &SearchLimits::depth(3), |iteration| {
+            depths.push(iteration.depth)
+        })
+        .unwrap();
+
+    assert_eq!(depths, [1, 2, 3]);
+    assert_eq!(result.depth, 3);
+}
+
+#[test]
+fn node_limit() {
+    let board = Board::from_fen(KIWIPETE).unwrap();
+    let limits = SearchLimits {
+        nodes: Some(5_000),
+        ..SearchLimits::default()
+    };
+    let result = Ai::new().search(&board, &limits).unwrap();
+
+    // The limit is only checked every so many nodes, and the first iteration
+    // always completes
+    assert!(result.nodes < 5_000 + 1024, "{} nodes", result.nodes);
+    assert!(result.depth < 5);
+}
+
+#[test]
+fn movetime_limit() {
+    let board = Board::from_fen(KIWIPETE).unwrap();
+    let start = Instant::now();
+    let result = Ai::new()
+        .search(&board, &SearchLimits::movetime(200))
+        .unwrap();
+
+    assert!(start.elapsed() < Duration::from_millis(1_000));
+    assert!(result.time < 1_000);
+
+    // JS gets a default time instead of searching to the maximum depth
+    let start = Instant::now();
+    Ai::new().js_search(&board, None, None).unwrap();
+    assert!(start.elapsed() < Duration::from_millis(DEFAULT_MOVETIME + 1_000));
+}
+
+#[test]
+fn stop_flag() {
+    let board = Board::from_fen(KIWIPETE).unwrap();
+
+    // A search stopped right away still completes its first iteration
+    let stop = Arc::new(AtomicBool::new(true));
+    let mut ai = Ai::new();
+    ai.set_stop_flag(Arc::clone(&stop));
+    let result = ai.search(&board, &SearchLimits::default()).unwrap();
+    assert_eq!(result.depth, 1);
+
+    // A search without limits runs until it is stopped from another thread
+    stop.store(false, Ordering::Relaxed);
+    let stopper = {
+        let stop = Arc::clone(&stop);
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            stop.store(true, Ordering::Relaxed);
+        })
+    };
+    let start = Instant::now();
+    let result = ai.search(&board, &SearchLimits::default()).unwrap();
+    stopper.join().unwrap();
+
+    assert!(start.elapsed() < Duration::from_millis(1_200));
+    assert!(result.depth >= 1);
+}
@@ -0,0 +1,114 @@
+//! How and when a game ends.
+
+use vite_wasm_functions::board::Board;
+use vite_wasm_functions::moves::Move;
+use vite_wasm_functions::status::GameStatus;
+
+fn status(fen: &str) -> GameStatus {
+    Board::from_fen(fen).unwrap().status()
+}
+
+fn play(board: &mut Board, moves: &[&str]) {
+    for uci in moves {
+        Move::from_uci(uci).unwrap().execute(board);
+    }
+}
+
+#[test]
+fn checkmate() {
+    let mut board = Board::default();
+    play(&mut board, &["f2f3", "e7e5", "g2g4", "d8h4"]);
+    assert_eq!(board.status(), GameStatus::BlackWins);
+    assert_eq!(board.status().winner(), Some(board.turn.next()));
+
+    assert_eq!(
+        status("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1"),
+        GameStatus::WhiteWins
+    );
+    // Check alone is not mate
+    assert_eq!(status("k7/8/1K6/8/8/8/8/7Q b - - 0 1"), GameStatus::Ongoing);
+}
+
+#[test]
+fn stalemate() {
+    assert_eq!(
+        status("k7/8/1Q6/8/8/8/8/7K b - - 0 1"),
+        GameStatus::Stalemate
+    );
+    assert!(GameStatus::Stalemate.is_draw());
+}
+
+#[test]
+fn threefold_repetition() {
+    let knights_out_and_back = ["g1f3", "g8f6", "f3g1", "f6g8"];
+
+    let mut board = Board::default();
+    assert_eq!(board.repetitions(), 1);
+    play(&mut board, &knights_out_and_back);
+    assert_eq!(board.repetitions(), 2);
+    assert_eq!(board.status(), GameStatus::Ongoing);
+    play(&mut board, &knights_out_and_back);
+    assert_eq!(board.repetitions(), 3);
+    assert_eq!(board.status(), GameStatus::ThreefoldRepetition);
+
+    // A pawn move makes the earlier positions unreachable
+    play(&mut board, &["e2e3", "e7e6"]);
+    play(&mut board, &knights_out_and_back);
+    assert_eq!(board.repetitions(), 2);
+
+    // A double push no pawn can capture leaves no en-passant square, so the
+    // position after it already counts
+    let mut board = Board::default();
+    play(
+        &mut board,
+        &[
+            "e2e4", "g8f6", "g1f3", "f6g8", "f3g1", "g8f6", "g1f3", "f6g8", "f3g1",
+        ],
+    );
+    assert_eq!(board.repetitions(), 3);
+    assert_eq!(board.status(), GameStatus::ThreefoldRepetition);
+}
+
+#[test]
+fn fifty_move_rule() {
+    let mut board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").unwrap();
+    assert_eq!(board.status(), GameStatus::Ongoing);
+    play(&mut board, &["a1a2"]);
+    assert_eq!(board.status(), GameStatus::FiftyMoveRule);
+
+    // A capture or pawn move resets the clock
+    let mut board = Board::from_fen("4k3/8/8/8/8/8/r7/R3K3 w - - 99 80").unwrap();
+    play(&mut board, &["a1a2"]);
+    assert_eq!(board.status(), GameStatus::Ongoing);
+    assert_eq!(board.halfmove_clock(), 0);
+}
+
+#[test]
+fn mate_on_the_fiftieth_move_counts() {
+    assert_eq!(
+        status("k7/1Q6/1K6/8/8/8/8/8 b - - 100 80"),
+        GameStatus::WhiteWins
+    );
+}
+
+#[test]
+fn insufficient_material() {
+    for fen in [
+        "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/2B1K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/1N2K3 w - - 0 1",
+        // Bishops which all stay on light squares
+        "2b1k3/8/8/8/8/8/8/4KB2 w - - 0 1",
+    ] {
+        assert_eq!(status(fen), GameStatus::InsufficientMaterial, "{}", fen);
+    }
+
+    for fen in [
+        "4k3/8/8/8/8/8/8/R3K3 w - - 0 1",
+        "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1",
+        "2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1",
+    ] {
+        assert_eq!(status(fen), GameStatus::Ongoing, "{}", fen);
+    }
+}
@@ -0,0 +1,98 @@
+//! Tactical positions with a known solution the search has to find.
+
+use vite_wasm_functions::ai::{Ai, SearchLimits};
+use vite_wasm_functions::board::{Board, Player};
+use vite_wasm_functions::moves::Move;
+use vite_wasm_functions::status::GameStatus;
+
+fn best_move(fen: &str, depth: usize) -> Move {
+    let board = Board::from_fen(fen).unwrap();
+    Ai::new()
+        .best_move(&board, &SearchLimits::depth(depth))
+        .expect("no move found")
+}
+
+fn assert_best_move(fen: &str, depth: usize, expected: &str) {
+    assert_eq!(best_move(fen, depth).to_uci(), expected, "{}", fen);
+}
+
+/// Lets the search play both sides for `moves` moves of the attacker and
+/// checks that the attacker has mated by then.
+fn assert_mates_in(fen: &str, moves: usize) {
+    let mut board = Board::from_fen(fen).unwrap();
+    let attacker = board.turn;
+    let depth = 2 * moves - 1;
+
+    for _ in 0..2 * moves - 1 {
+        let move_ = Ai::new()
+            .best_move(&board, &SearchLimits::depth(depth))
+            .unwrap_or_else(|| panic!("{}: game ended early", fen));
+        move_.execute(&mut board);
+    }
+
+    let mated = match attacker {
+        Player::White => GameStatus::WhiteWins,
+        Player::Black => GameStatus::BlackWins,
+    };
+    assert_eq!(board.status(), mated, "{} ended in {}", fen, board.to_fen());
+}
+
+#[test]
+fn mate_in_one() {
+    // Back rank mate
+    assert_best_move("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 2, "a1a8");
+    assert_best_move("r5k1/8/8/8/8/8/5PPP/6K1 b - - 0 1", 2, "a8a1");
+    // Scholar's mate
+    assert_best_move(
+        "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+        3,
+        "h5f7",
+    );
+    // Mate with a promotion, to a queen or a rook
+    assert_mates_in("k7/4P3/1K6/8/8/8/8/8 w - - 0 1", 1);
+}
+
+#[test]
+fn mate_in_two() {
+    // King and rook against king, the king has to come closer first
+    assert_mates_in("k7/8/2K5/8/8/8/8/7R w - - 0 1", 2);
+    assert_mates_in("7r/8/8/8/8/2k5/8/K7 b - - 0 1", 2);
+}
+
+#[test]
+fn wins_hanging_material() {
+    // An undefended queen
+    assert_best_move("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 3, "d2d5");
+    assert_best_move("4k3/3r4/8/8/3Q4/8/8/4K3 b - - 0 1", 3, "d7d4");
+    // A knight fork of king and queen
+    assert_best_move("4k3/8/8/1q1N4/8/8/8/4K3 w - - 0 1", 3, "d5c7");
+}
+
+#[test]
+fn sees_recaptures_past_the_horizon() {
+    // Taking the pawn loses the queen to the recapture one ply later
+    let move_ = best_move("4k3/8/2p5/3p4/8/8/3Q4/4K3 w - - 0 1", 1);
+    assert_ne!(move_.to_uci(), "d2d5");
+    let move_ = best_move("4k3/3q4/8/8/3P4/2P5/8/4K3 b - - 0 1", 1);
+    assert_ne!(move_.to_uci(), "d7d4");
+}
+
+#[test]
+fn reports_the_distance_to_mate() {
+    let search = |fen: &str, depth: usize| {
+        let board = Board::from_fen(fen).unwrap();
+        Ai::new()
+            .search(&board, &SearchLimits::depth(depth))
+            .expect("no move found")
+    };
+
+    // A mate in one is found and preferred over the slower ones
+    let result = search("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 4);
+    assert_eq!(result.mate, Some(1));
+    assert_eq!(result.best_move.to_uci(), "a1a8");
+
+    assert_eq!(search("k7/8/2K5/8/8/8/8/7R w - - 0 1", 3).mate, Some(2));
+
+    // The defending side sees its loss coming
+    assert_eq!(search("k7/8/1K6/8/8/8/8/7R b - - 0 1", 2).mate, Some(-1));
+}
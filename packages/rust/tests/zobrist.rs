@@ -0,0 +1,79 @@
+//! The incrementally updated Zobrist hashes have to match hashes computed
+//! from scratch after every move and after taking every move back.
+
+use vite_wasm_functions::board::Board;
+use vite_wasm_functions::moves::Move;
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+const EN_PASSANT: &str = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3";
+
+/// Walks the whole move tree `depth` plies deep, checking the hash at every node.
+fn assert_hashes(board: &mut Board, depth: usize) {
+    assert_eq!(board.hash(), board.compute_hash(), "{}", board.to_fen());
+    assert_eq!(
+        board.pawn_hash(),
+        board.compute_pawn_hash(),
+        "{}",
+        board.to_fen()
+    );
+    if depth == 0 {
+        return;
+    }
+
+    for move_ in board.generate_all_moves() {
+        let before = (board.hash(), board.pawn_hash());
+        let fen = board.to_fen();
+
+        let undo = board.make_move(&move_);
+        assert_hashes(board, depth - 1);
+        board.unmake_move(&move_, undo);
+
+        let after = (board.hash(), board.pawn_hash());
+        assert_eq!(after, before, "{} after {}", fen, move_.to_uci());
+        assert_eq!(board.to_fen(), fen);
+    }
+}
+
+#[test]
+fn incremental_hash_matches_recomputation() {
+    assert_hashes(&mut Board::default(), 3);
+    for fen in [KIWIPETE, POSITION_4, EN_PASSANT] {
+        assert_hashes(&mut Board::from_fen(fen).unwrap(), 2);
+    }
+}
+
+#[test]
+fn transpositions_hash_equally() {
+    let play = |moves: &[&str]| {
+        let mut board = Board::default();
+        for uci in moves {
+            Move::from_uci(uci).unwrap().execute(&mut board);
+        }
+        board
+    };
+
+    let knights_back = play(&["g1f3", "g8f6", "f3g1", "f6g8"]);
+    assert_eq!(knights_back.hash(), Board::default().hash());
+
+    let a = play(&["g1f3", "b8c6", "b1c3"]);
+    let b = play(&["b1c3", "b8c6", "g1f3"]);
+    assert_eq!(a.hash(), b.hash());
+
+    // The same pieces with the other side to move, or without a usable
+    // en-passant square, are different positions
+    let white_to_move = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    let black_to_move = Board::from_fen("4k3/8/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+    assert_ne!(white_to_move.hash(), black_to_move.hash());
+
+    let double_push = play(&["e2e4", "a7a6", "e4e5", "d7d5"]);
+    let without_en_passant =
+        Board::from_fen("rnbqkbnr/1pp1pppp/p7/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3").unwrap();
+    assert_ne!(double_push.hash(), without_en_passant.hash());
+
+    // A double push no pawn can capture does not change the position
+    let double_push = play(&["e2e4"]);
+    let without_en_passant =
+        Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+    assert_eq!(double_push.hash(), without_en_passant.hash());
+}